@@ -1,4 +1,3 @@
-use std::time::Duration;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
@@ -15,13 +14,14 @@ async fn main() -> Result<(), failure::Error> {
 
     let publisher = rosty::publish::<rosty_msg::std_msgs::String>("/foo", 8).await?;
 
+    let mut rate = rosty::rate(1000.0);
     let mut counter = 0;
     while !rosty::is_awaiting_shutdown() {
         let mut msg = rosty_msg::std_msgs::String::default();
         msg.data = format!("Hello, timmie! {}", counter);
         counter += 1;
         publisher.send(msg).await.unwrap();
-        tokio::time::delay_for(Duration::from_millis(1)).await;
+        rate.sleep().await;
     }
 
     // Run the node until it quits
@@ -12,31 +12,81 @@ mod rosxmlrpc;
 mod shutdown_token;
 mod tcpros;
 
-pub use crate::node::Topic;
+pub use crate::shutdown_token::ShutdownReason;
+
+pub use crate::node::{
+    ClockJump, ClockJumps, ConnectionInfo, ConnectionStats, Direction, LoggerLevels,
+    MessageDescription, ParamWatch, Rate, RawMessage, RosoutLayer, ServiceCallError,
+    SubscriberEvent, Timer, TimerEvent, Topic, ROOT_LOGGER,
+};
 use crate::node::{Publisher, PublisherError};
+use crate::node::{Service, ServiceClient, ServiceError};
 use crate::node::{Subscriber, SubscriptionError};
 use crate::rosxmlrpc::Response;
-use crate::tcpros::Message;
-use node::{Node, NodeArgs, Param};
+use crate::tcpros::{Message, ServicePair};
+pub use node::{NameError, Node, NodeArgs, Param};
 
 use rosty_msg::Time;
 use serde::Deserialize;
+use std::future::Future;
+use std::time::Duration;
 
-/// The instance that represents this node.
+/// The default node that the free functions of this crate operate on.
 static NODE: Lazy<ShardedLock<Option<Node>>> = Lazy::new(|| ShardedLock::new(None));
 
-/// Initializes the ROS node
+/// Initializes the default ROS node
 pub async fn init<S: AsRef<str>>(default_name: S) -> Result<(), failure::Error> {
     init_with_args(NodeArgs::new(default_name), true).await
 }
 
-/// Initializes the ROS node
+/// Returns options to initialize the ROS node with, see `InitOptions`
+pub fn init_options<S: AsRef<str>>(default_name: S) -> InitOptions {
+    InitOptions {
+        args: NodeArgs::new(default_name),
+        capture_sigint: true,
+    }
+}
+
+/// A builder for the options of the ROS node, created with `init_options`
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    args: NodeArgs,
+    capture_sigint: bool,
+}
+
+impl InitOptions {
+    /// Makes the name of the node unique by appending a suffix, so multiple instances of the same
+    /// node can run at the same time.
+    pub fn anonymous(mut self, anonymous: bool) -> Self {
+        self.args.anonymous = anonymous;
+        self
+    }
+
+    /// Whether the node is shut down when the process is interrupted (Ctrl+C), on by default.
+    pub fn capture_sigint(mut self, capture_sigint: bool) -> Self {
+        self.capture_sigint = capture_sigint;
+        self
+    }
+
+    /// Modifies the arguments of the node
+    pub fn args(mut self, configure: impl FnOnce(NodeArgs) -> NodeArgs) -> Self {
+        self.args = configure(self.args);
+        self
+    }
+
+    /// Initializes the ROS node
+    pub async fn init(self) -> Result<(), failure::Error> {
+        init_with_args(self.args, self.capture_sigint).await
+    }
+}
+
+/// Initializes the default ROS node. Additional nodes can be constructed with `Node::new`.
 pub async fn init_with_args(args: NodeArgs, capture_sigint: bool) -> Result<(), failure::Error> {
     let mut singleton = NODE
         .write()
         .expect("Could not acquire write lock to singleton ROS node");
     if singleton.is_some() {
-        bail!("There can only be a single ROS node");
+        bail!("The default ROS node has already been initialized");
     }
 
     let node = Node::new(args).await?;
@@ -44,7 +94,7 @@ pub async fn init_with_args(args: NodeArgs, capture_sigint: bool) -> Result<(),
     if capture_sigint {
         let shutdown_sender = node.shutdown_token.clone();
         ctrlc::set_handler(move || {
-            shutdown_sender.shutdown();
+            shutdown_sender.shutdown_with_reason(ShutdownReason::Interrupted);
         })?;
     }
 
@@ -53,7 +103,7 @@ pub async fn init_with_args(args: NodeArgs, capture_sigint: bool) -> Result<(),
     Ok(())
 }
 
-/// Returns true if the ROS node has been initialized
+/// Returns true if the default ROS node has been initialized
 pub fn is_initialized() -> bool {
     NODE.read()
         .expect("Could not acquire read lock to singleton ROS node")
@@ -70,17 +120,82 @@ macro_rules! node {
     };
 }
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
+/// Returns a handle to the default ROS node
+///
+/// # Panics
+///
+/// If the default node has not been initialized
+pub fn default_node() -> Node {
+    node!().clone()
+}
+
 /// Returns 'now' as a Time object
 /// # Situations
 /// * If the node is run normally the current time is returned. Ros calls this WallTime.
 ///   This can panic if the SystemTime cannot be retrieved
 /// * If the node is run in simulated time i.e. `/use_sim_time` is true. Then the simulated
 ///   time is returned. In this case a panic could occur if the `/clock` topic has not
-///   been published
+///   been published, use `try_now` or `wait_for_valid_time` to prevent this
 pub fn now() -> Time {
     node!().now().expect("clock is not yet available")
 }
 
+/// Returns 'now' as a Time object or `None` if the clock is not yet available, which is the case
+/// with simulated time until the first message on `/clock` is received.
+pub fn try_now() -> Option<Time> {
+    node!().now()
+}
+
+/// Waits for `duration` according to the clock of the node. With simulated time the duration
+/// starts when the first clock message is received and the wait pauses with the simulation.
+/// Returns false if the wait was interrupted because the time jumped backwards.
+pub async fn sleep(duration: Duration) -> bool {
+    default_node().sleep(duration).await
+}
+
+/// Waits until the clock of the node reaches `time`. Returns false if the wait was interrupted
+/// because the time jumped backwards.
+pub async fn sleep_until(time: Time) -> bool {
+    default_node().sleep_until(time).await
+}
+
+/// Waits at most `timeout` of wall time until the clock of the node is valid. Returns the current
+/// time or `None` if the timeout elapsed.
+pub async fn wait_for_valid_time(timeout: Duration) -> Option<Time> {
+    default_node().wait_for_valid_time(timeout).await
+}
+
+/// Returns a stream of the jumps of the clock, for instance when a simulator or bag player
+/// restarts. Backwards jumps are always reported, forward jumps only if they are larger than
+/// `forward_threshold`.
+pub fn clock_jumps(forward_threshold: Option<Duration>) -> ClockJumps {
+    node!().clock_jumps(forward_threshold)
+}
+
+/// Returns a `Rate` that runs a loop `frequency` times per second. With simulated time the rate
+/// follows the `/clock` topic.
+///
+/// # Panics
+///
+/// Panics if `frequency` is not a finite positive number.
+pub fn rate(frequency: f64) -> Rate {
+    node!().rate(frequency)
+}
+
+/// Returns a `Timer` that expires every `period`. With simulated time the timer follows the
+/// `/clock` topic.
+pub fn timer(period: Duration) -> Timer {
+    node!().timer(period)
+}
+
+/// Returns a `Timer` that expires once, `delay` after it is first awaited
+pub fn oneshot_timer(delay: Duration) -> Timer {
+    node!().oneshot_timer(delay)
+}
+
 /// Returns the URI of this node
 pub fn uri() -> String {
     node!().uri().to_owned()
@@ -106,12 +221,19 @@ pub fn is_using_sim_time() -> bool {
     node!().is_using_sim_time()
 }
 
+/// Resolves a name relative to the node and applies the remappings of the node. Relative names are
+/// resolved in the namespace of the node and private names (`~name`) in the namespace of the node
+/// itself.
+pub fn resolve_name(name: &str) -> Result<String, NameError> {
+    node!().resolve_name(name)
+}
+
 pub async fn topics() -> Response<Vec<Topic>> {
-    node!().topics().await
+    default_node().topics().await
 }
 
 pub async fn param_names() -> Response<Vec<String>> {
-    node!().get_all_param_names().await
+    default_node().get_all_param_names().await
 }
 
 pub fn param(key: impl AsRef<str>) -> Param {
@@ -126,19 +248,26 @@ pub fn param(key: impl AsRef<str>) -> Param {
 /// * /global_example
 ///in this order.
 pub async fn search_param<'a, T: Deserialize<'a>, S: AsRef<str>>(key: S) -> Response<T> {
-    node!().search_param(key.as_ref()).await
+    default_node().search_param(key.as_ref()).await
 }
 
 pub async fn run() {
-    node!().run().await
+    default_node().run().await
 }
 
 pub fn shutdown() {
-    node!().shutdown_token.shutdown();
+    node!().shutdown();
+}
+
+/// Returns why the node was shut down, or `None` if it is still running. When another node
+/// registered with the same name, the reason is `ShutdownReason::Remote` with the message of the
+/// master.
+pub fn shutdown_reason() -> Option<ShutdownReason> {
+    node!().shutdown_reason()
 }
 
 pub fn is_awaiting_shutdown() -> bool {
-    node!().shutdown_token.is_awaiting_shutdown()
+    node!().is_awaiting_shutdown()
 }
 
 /// Connect to a topic
@@ -146,12 +275,107 @@ pub async fn subscribe<T: Message>(
     topic: &str,
     queue_size: usize,
 ) -> Result<Subscriber<T>, SubscriptionError> {
-    node!().subscribe::<T>(topic, queue_size).await
+    default_node().subscribe::<T>(topic, queue_size).await
+}
+
+/// Connect to a topic without knowing the type of its messages. The messages are received in their
+/// serialized form together with the connection header of their publisher.
+pub async fn subscribe_raw(
+    topic: &str,
+    queue_size: usize,
+) -> Result<Subscriber<RawMessage>, SubscriptionError> {
+    default_node().subscribe_raw(topic, queue_size).await
+}
+
+/// Subscribes to a topic, waits at most `timeout` for the first message and unsubscribes again.
+/// Returns `None` if no message was received in time.
+pub async fn wait_for_message<T: Message>(
+    topic: &str,
+    timeout: Duration,
+) -> Result<Option<T>, SubscriptionError> {
+    default_node().wait_for_message(topic, timeout).await
 }
 
 pub async fn publish<T: Message>(
     topic: &str,
     queue_size: usize,
 ) -> Result<Publisher<T>, PublisherError> {
-    node!().publish(topic, queue_size).await
+    default_node().publish(topic, queue_size).await
+}
+
+/// Publish to a topic with a message type that is specified at runtime by `description`. The
+/// publisher sends messages that are already serialized, for instance the `data` of a `RawMessage`.
+pub async fn publish_raw(
+    topic: &str,
+    description: MessageDescription,
+    queue_size: usize,
+) -> Result<Publisher<RawMessage>, PublisherError> {
+    default_node()
+        .publish_raw(topic, description, queue_size)
+        .await
+}
+
+/// Same as `publish_raw` but the last message sent is stored and sent to every subscriber that
+/// connects later on.
+pub async fn publish_raw_latched(
+    topic: &str,
+    description: MessageDescription,
+    queue_size: usize,
+) -> Result<Publisher<RawMessage>, PublisherError> {
+    default_node()
+        .publish_raw_latched(topic, description, queue_size)
+        .await
+}
+
+/// Publish to a topic with latching enabled. The last message sent is stored and immediately sent
+/// to every subscriber that connects later on.
+pub async fn publish_latched<T: Message>(
+    topic: &str,
+    queue_size: usize,
+) -> Result<Publisher<T>, PublisherError> {
+    default_node().publish_latched(topic, queue_size).await
+}
+
+/// Advertise a service with the master. Every incoming request is passed to `handler`; an `Err`
+/// returned by the handler is sent back to the caller as the failure message.
+pub async fn advertise_service<S, F, R>(service: &str, handler: F) -> Result<Service, ServiceError>
+where
+    S: ServicePair,
+    F: Fn(S::Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<S::Response, String>> + Send + 'static,
+{
+    default_node()
+        .advertise_service::<S, _, _>(service, handler)
+        .await
+}
+
+/// Advertises the `~get_loggers` and `~set_logger_level` services, which list and change the
+/// levels of `levels` while the node runs.
+pub async fn advertise_logger_services<S>(
+    levels: LoggerLevels<S>,
+) -> Result<(Service, Service), ServiceError>
+where
+    S: tracing::Subscriber + Send + Sync + 'static,
+{
+    default_node().advertise_logger_services(levels).await
+}
+
+/// Returns a client for the specified service. A new connection is made for every call.
+pub fn service_client<S: ServicePair>(service: &str) -> Result<ServiceClient<S>, ServiceCallError> {
+    node!().service_client(service, false)
+}
+
+/// Returns a client for the specified service that keeps its connection open between calls. When
+/// the connection is lost the service is looked up again and a new connection is made.
+pub fn persistent_service_client<S: ServicePair>(
+    service: &str,
+) -> Result<ServiceClient<S>, ServiceCallError> {
+    node!().service_client(service, true)
+}
+
+/// Waits until the specified service is available. The service is first looked up with the master
+/// and then probed to make sure its server is actually accepting connections. Returns
+/// `ServiceCallError::Timeout` if the service did not become available within `timeout`.
+pub async fn wait_for_service(service: &str, timeout: Duration) -> Result<(), ServiceCallError> {
+    default_node().wait_for_service(service, timeout).await
 }
@@ -1,54 +1,116 @@
 mod args;
 mod clock;
 mod error;
+mod logger_levels;
 mod master;
+mod names;
+mod param_watch;
 mod publisher;
+mod rate;
+mod rosout;
+mod service;
+mod service_client;
 mod simtime;
 mod slave;
 mod subscriber;
+mod timer;
 mod topic;
 
 pub use args::NodeArgs;
+use args::ParamValue;
 use master::Master;
+pub use names::NameError;
+use names::Resolver;
 use slave::Slave;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-pub use self::{error::SubscriptionError, publisher::Publisher, subscriber::Subscriber};
-pub use crate::tcpros::PublisherError;
-use crate::{rosxmlrpc::Response, shutdown_token::ShutdownToken, tcpros::Message};
-pub use master::Topic;
+pub use self::{
+    error::SubscriptionError,
+    logger_levels::{LoggerLevels, ROOT_LOGGER},
+    param_watch::ParamWatch,
+    publisher::Publisher,
+    rate::Rate,
+    rosout::RosoutLayer,
+    service::Service,
+    service_client::ServiceClient,
+    subscriber::Subscriber,
+    timer::{Timer, TimerEvent},
+};
+pub use crate::tcpros::{
+    ConnectionInfo, ConnectionStats, Direction, MessageDescription, PublisherError, RawMessage,
+    ServiceCallError, ServiceError, SubscriberEvent,
+};
+use crate::{
+    rosxmlrpc::{from_value, Response, ResponseError, Value},
+    shutdown_token::{ShutdownReason, ShutdownToken},
+    tcpros::{probe_service, Message, ServicePair, SubscriberMessage},
+};
+use futures::{future, StreamExt};
+use logger_levels::parse_level;
+pub use master::{SystemState, Topic};
+use rosty_msg::roscpp::{GetLoggers, GetLoggersRes, Logger, SetLoggerLevel, SetLoggerLevelRes};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing_futures::Instrument;
 
 use clock::Clock;
+pub use clock::{ClockJump, ClockJumps};
 use rosty_msg::Time;
 use simtime::SimTime;
 
 /// Represents a param on the parameter server
 pub struct Param {
-    name: String,
+    /// The global name of the parameter, after remapping
+    key: Result<String, NameError>,
     master: Arc<Master>,
+    slave: Arc<Slave>,
 }
 
 impl Param {
-    fn new(name: impl AsRef<str>, master: Arc<Master>) -> Param {
-        Param {
-            name: name.as_ref().to_string(),
-            master,
-        }
+    fn new(key: Result<String, NameError>, master: Arc<Master>, slave: Arc<Slave>) -> Param {
+        Param { key, master, slave }
     }
 
-    /// Get the value from the parameter server
+    /// Returns the global name of the parameter
+    pub fn name(&self) -> Response<&str> {
+        self.key
+            .as_ref()
+            .map(String::as_str)
+            .map_err(|e| ResponseError::Client(e.to_string()))
+    }
+
+    /// Get the value from the parameter server. While the parameter is watched the value is taken
+    /// from the local copy instead.
     pub async fn get<'a, T: Deserialize<'a>>(&self) -> Response<T> {
-        self.master.get_param(&self.name).await
+        let key = self.name()?;
+        match self.slave.cached_param(key).await {
+            // The master signals a parameter that does not exist with an empty struct
+            Some(Value::Struct(members)) if members.is_empty() => Err(ResponseError::Client(
+                format!("parameter [{}] is not set", key),
+            )),
+            Some(value) => from_value(value),
+            None => self.master.get_param(key).await,
+        }
+    }
+
+    /// Returns a stream of the values of this parameter. The master notifies the node about every
+    /// change so the parameter does not have to be polled.
+    pub async fn watch<T: DeserializeOwned>(&self) -> Response<ParamWatch<T>> {
+        ParamWatch::new(self.slave.clone(), self.name()?).await
     }
 
     /// Set the value on the ROS parameter server
     pub async fn set<T: Serialize>(&self, value: &T) -> Response<()> {
+        let key = self.name()?;
+        // The master sends the new values of the parameters that are watched
+        self.slave.invalidate_cached_params(key).await;
         self.master
-            .set_param(&self.name, value)
+            .set_param(key, value)
             .await
             // We can ignore the i32, because the ROS standard says it is ignorable
             .map(|_val: i32| ())
@@ -56,8 +118,10 @@ impl Param {
 
     /// Delete the parameter from the ROS parameter server
     pub async fn delete(&self) -> Response<()> {
+        let key = self.name()?;
+        self.slave.invalidate_cached_params(key).await;
         self.master
-            .delete_param(&self.name)
+            .delete_param(key)
             .await
             // We can ignore the i32, because the ROS standard says it is ignorable
             .map(|_val: i32| ())
@@ -65,7 +129,7 @@ impl Param {
 
     /// Check if this parameter already exists on the ROS parameter server
     pub async fn exists(&self) -> Response<bool> {
-        self.master.has_param(&self.name).await
+        self.master.has_param(self.name()?).await
     }
 }
 
@@ -75,18 +139,26 @@ impl Param {
 ///  * A slave API. The slave API is an XMLRPC API that has two roles: receiving callbacks from the
 ///    master, and negotiating connections with other nodes.
 ///  * A topic transport protocol
+///
+/// A `Node` is a handle, clones refer to the same node. Several nodes, with different names,
+/// namespaces or masters, can run in the same process. The free functions of this crate operate on
+/// a default node that is initialized with `init`.
+#[derive(Clone)]
 pub struct Node {
     slave: Arc<Slave>,
     master: Arc<Master>,
     hostname: String,
     bind_address: String,
     name: String,
+    resolver: Resolver,
     result: Arc<Mutex<Option<Result<(), failure::Error>>>>,
     clock: Arc<Clock>,
-    pub shutdown_token: ShutdownToken,
+    pub(crate) shutdown_token: ShutdownToken,
 }
 
 impl Node {
+    /// Constructs a new node and registers it with the master specified in `args`. The node runs
+    /// until it is shut down.
     pub async fn new(args: NodeArgs) -> Result<Self, failure::Error> {
         let shutdown_token = ShutdownToken::default();
 
@@ -107,7 +179,11 @@ impl Node {
                 name
             )
         }
-        let name = format!("{}/{}", namespace, name);
+        let name = if args.anonymous {
+            format!("{}/{}_{}", namespace, name, anonymous_suffix())
+        } else {
+            format!("{}/{}", namespace, name)
+        };
 
         // Construct the master API client
         let master = Arc::new(Master::new(&args.master_uri, &name)?);
@@ -127,6 +203,17 @@ impl Node {
         // Get the URI of the master to check if the master is available
         master.get_uri().await?;
 
+        // Set the private parameters before any user code runs
+        let resolver = Resolver::new(&name, &args.remappings);
+        for (param, value) in &args.params {
+            match resolver.resolve(&format!("~{}", param)) {
+                Ok(key) => {
+                    master.set_param(&key, &ParamValue::parse(value)).await?;
+                }
+                Err(e) => warn!("ignoring parameter _{}:={}: {}", param, value, e),
+            }
+        }
+
         // Start the slave
         let result_mutex = Arc::new(Mutex::new(None));
         let join_handle_mutex = result_mutex.clone();
@@ -136,7 +223,12 @@ impl Node {
         });
 
         // Check if we need to use simtime
-        let param = Param::new("/use_sim_time", master.clone());
+        let slave = Arc::new(slave);
+        let param = Param::new(
+            Ok("/use_sim_time".to_owned()),
+            master.clone(),
+            slave.clone(),
+        );
 
         // Try to get the sim_time, and open a topic if we are waiting for it
         let sim_time = if param.exists().await? && param.get::<bool>().await? {
@@ -148,10 +240,11 @@ impl Node {
         let clock = Arc::new(Clock { sim_time });
 
         let node = Node {
-            slave: Arc::new(slave),
+            slave,
             master,
             hostname: args.hostname.to_owned(),
             bind_address: bind_host.to_owned(),
+            resolver,
             name,
             result: result_mutex,
             shutdown_token,
@@ -193,6 +286,22 @@ impl Node {
         }
     }
 
+    /// Shuts down the node
+    pub fn shutdown(&self) {
+        self.shutdown_token
+            .shutdown_with_reason(ShutdownReason::Requested);
+    }
+
+    /// Returns true if the node is shutting down
+    pub fn is_awaiting_shutdown(&self) -> bool {
+        self.shutdown_token.is_awaiting_shutdown()
+    }
+
+    /// Returns why the node was shut down, or `None` if it is still running
+    pub fn shutdown_reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_token.reason()
+    }
+
     /// Returns true if this node is using the simulated time
     pub fn is_using_sim_time(&self) -> bool {
         self.clock.is_using_sim_time()
@@ -203,27 +312,101 @@ impl Node {
         self.clock.now()
     }
 
+    /// Waits for `duration` according to the clock of the node. With simulated time the duration
+    /// starts when the first clock message is received. Returns false if the wait was interrupted
+    /// because the time jumped backwards.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        let now = self.clock.wait_for_now().await;
+        self.clock.sleep_until(now + duration.into()).await
+    }
+
+    /// Waits until the clock of the node reaches `time`. Returns false if the wait was interrupted
+    /// because the time jumped backwards.
+    pub async fn sleep_until(&self, time: Time) -> bool {
+        self.clock.sleep_until(time).await
+    }
+
+    /// Waits at most `timeout` of wall time until the clock of the node is valid, which with
+    /// simulated time is when the first clock message is received. Returns the current time or
+    /// `None` if the timeout elapsed.
+    pub async fn wait_for_valid_time(&self, timeout: Duration) -> Option<Time> {
+        tokio::time::timeout(timeout, self.clock.wait_for_now())
+            .await
+            .ok()
+    }
+
+    /// Returns a stream of the jumps of the clock of the node, for instance when a simulator or bag
+    /// player restarts. Backwards jumps are always reported, forward jumps only if they are larger
+    /// than `forward_threshold`. Only simulated time is checked for jumps.
+    pub fn clock_jumps(&self, forward_threshold: Option<Duration>) -> ClockJumps {
+        self.clock.jumps(forward_threshold.map(Into::into))
+    }
+
+    /// Returns a `Rate` that runs a loop `frequency` times per second according to the clock of
+    /// the node
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frequency` is not a finite positive number.
+    pub fn rate(&self, frequency: f64) -> Rate {
+        Rate::new(self.clock.clone(), frequency)
+    }
+
+    /// Returns a `Timer` that expires every `period` according to the clock of the node
+    pub fn timer(&self, period: Duration) -> Timer {
+        Timer::new(self.clock.clone(), period.into(), false)
+    }
+
+    /// Returns a `Timer` that expires once, `delay` after it is first awaited
+    pub fn oneshot_timer(&self, delay: Duration) -> Timer {
+        Timer::new(self.clock.clone(), delay.into(), true)
+    }
+
     /// Returns a list of all topics
     pub async fn topics(&self) -> Response<Vec<Topic>> {
         self.master.get_topic_types().await
     }
 
+    /// Returns the publishers, subscribers and services registered with the master
+    pub async fn system_state(&self) -> Response<SystemState> {
+        self.master.get_system_state().await
+    }
+
     /// Returns a list of all parameter names
     pub async fn get_all_param_names(&self) -> Response<Vec<String>> {
         self.master.get_all_param_names().await
     }
 
+    /// Resolves a name relative to this node and applies the remappings of the node
+    pub fn resolve_name(&self, name: &str) -> Result<String, NameError> {
+        self.resolver.translate(name)
+    }
+
     /// Return a parameter
     pub fn param(&self, key: impl AsRef<str>) -> Param {
-        Param::new(key.as_ref(), self.master.clone())
+        Param::new(
+            self.resolver.translate(key.as_ref()),
+            self.master.clone(),
+            self.slave.clone(),
+        )
     }
 
     pub async fn search_param<'a, T: Deserialize<'a>>(&self, key: impl AsRef<str>) -> Response<T> {
-        self.master.search_param(key.as_ref()).await
+        // The key is searched for relative to the namespace of the node, unless it is remapped
+        let key = key.as_ref();
+        let invalid_name = |e: NameError| ResponseError::Client(e.to_string());
+        let resolved = self.resolver.resolve(key).map_err(invalid_name)?;
+        let translated = self.resolver.translate(key).map_err(invalid_name)?;
+        let key = if resolved != translated {
+            translated.as_str()
+        } else {
+            key
+        };
+        self.master.search_param(key).await
     }
 
     /// Connect to a topic
-    pub async fn subscribe<T: Message>(
+    pub async fn subscribe<T: SubscriberMessage>(
         &self,
         topic: &str,
         queue_size: usize,
@@ -233,28 +416,246 @@ impl Node {
         } else {
             queue_size
         };
-        Subscriber::new(Arc::clone(&self.slave), topic, queue_size)
-            .instrument(tracing::info_span!("subscribe", topic = topic))
+        let topic = self
+            .resolver
+            .translate(topic)
+            .map_err(SubscriptionError::InvalidName)?;
+        Subscriber::new(Arc::clone(&self.slave), &topic, queue_size)
+            .instrument(tracing::info_span!("subscribe", topic = topic.as_str()))
             .await
     }
 
+    /// Connect to a topic without knowing the type of its messages. The messages are received
+    /// in their serialized form together with the connection header of their publisher.
+    pub async fn subscribe_raw(
+        &self,
+        topic: &str,
+        queue_size: usize,
+    ) -> Result<Subscriber<RawMessage>, SubscriptionError> {
+        self.subscribe::<RawMessage>(topic, queue_size).await
+    }
+
+    /// Subscribes to a topic, waits at most `timeout` for the first message and unsubscribes again.
+    /// The subscription is unregistered from the master before this function returns. Returns
+    /// `None` if no message was received in time.
+    pub async fn wait_for_message<T: Message>(
+        &self,
+        topic: &str,
+        timeout: Duration,
+    ) -> Result<Option<T>, SubscriptionError> {
+        let mut subscriber = self.subscribe::<T>(topic, 1).await?;
+        let message = tokio::time::timeout(timeout, subscriber.next())
+            .await
+            .ok()
+            .flatten()
+            .map(|(_, message)| message);
+        subscriber.unsubscribe().await;
+        Ok(message)
+    }
+
     pub async fn publish<T: Message>(
         &self,
         topic: &str,
         queue_size: usize,
+    ) -> Result<Publisher<T>, PublisherError> {
+        self.publish_with_latching(topic, queue_size, false).await
+    }
+
+    /// Publish to a topic. The last message sent is stored and sent to every subscriber that
+    /// connects later on.
+    pub async fn publish_latched<T: Message>(
+        &self,
+        topic: &str,
+        queue_size: usize,
+    ) -> Result<Publisher<T>, PublisherError> {
+        self.publish_with_latching(topic, queue_size, true).await
+    }
+
+    /// Publish to a topic with a message type that is specified at runtime by `description`. The
+    /// publisher sends messages that are already serialized.
+    pub async fn publish_raw(
+        &self,
+        topic: &str,
+        description: MessageDescription,
+        queue_size: usize,
+    ) -> Result<Publisher<RawMessage>, PublisherError> {
+        self.publish_with_description(topic, queue_size, false, description)
+            .await
+    }
+
+    /// Same as `publish_raw` but the last message sent is stored and sent to every subscriber that
+    /// connects later on.
+    pub async fn publish_raw_latched(
+        &self,
+        topic: &str,
+        description: MessageDescription,
+        queue_size: usize,
+    ) -> Result<Publisher<RawMessage>, PublisherError> {
+        self.publish_with_description(topic, queue_size, true, description)
+            .await
+    }
+
+    async fn publish_with_latching<T: Message>(
+        &self,
+        topic: &str,
+        queue_size: usize,
+        latching: bool,
+    ) -> Result<Publisher<T>, PublisherError> {
+        self.publish_with_description(topic, queue_size, latching, MessageDescription::of::<T>())
+            .await
+    }
+
+    async fn publish_with_description<T>(
+        &self,
+        topic: &str,
+        queue_size: usize,
+        latching: bool,
+        description: MessageDescription,
     ) -> Result<Publisher<T>, PublisherError> {
         let queue_size = if queue_size == 0 {
             usize::max_value()
         } else {
             queue_size
         };
+        let topic = self
+            .resolver
+            .translate(topic)
+            .map_err(PublisherError::InvalidName)?;
         Publisher::new(
             Arc::clone(&self.slave),
             &self.hostname,
-            topic,
+            &topic,
             queue_size,
+            latching,
+            description,
             self.clock.clone(),
         )
         .await
     }
+
+    /// Advertise a service that is handled by the specified `handler`
+    pub async fn advertise_service<S, F, R>(
+        &self,
+        service: &str,
+        handler: F,
+    ) -> Result<Service, ServiceError>
+    where
+        S: ServicePair,
+        F: Fn(S::Request) -> R + Send + Sync + 'static,
+        R: Future<Output = Result<S::Response, String>> + Send + 'static,
+    {
+        let service = self
+            .resolver
+            .translate(service)
+            .map_err(ServiceError::InvalidName)?;
+        Service::new::<S, _, _>(Arc::clone(&self.slave), &self.hostname, &service, handler)
+            .instrument(tracing::info_span!(
+                "advertise_service",
+                service = service.as_str()
+            ))
+            .await
+    }
+
+    /// Advertises the `~get_loggers` and `~set_logger_level` services of roscpp, which list and
+    /// change the levels of `levels` while the node runs.
+    pub async fn advertise_logger_services<S>(
+        &self,
+        levels: LoggerLevels<S>,
+    ) -> Result<(Service, Service), ServiceError>
+    where
+        S: tracing::Subscriber + Send + Sync + 'static,
+    {
+        let get_levels = levels.clone();
+        let get_service = self
+            .advertise_service::<GetLoggers, _, _>("~get_loggers", move |_| {
+                let loggers = get_levels
+                    .loggers()
+                    .into_iter()
+                    .map(|(name, level)| Logger {
+                        name,
+                        level: level.to_string(),
+                    })
+                    .collect();
+                future::ready(Ok(GetLoggersRes { loggers }))
+            })
+            .await?;
+        let set_service = self
+            .advertise_service::<SetLoggerLevel, _, _>("~set_logger_level", move |request| {
+                let result = match parse_level(&request.level) {
+                    Some(level) => levels
+                        .set_level(&request.logger, level)
+                        .map(|_| SetLoggerLevelRes {})
+                        .map_err(|e| e.to_string()),
+                    None => Err(format!("unknown logger level '{}'", request.level)),
+                };
+                future::ready(result)
+            })
+            .await?;
+        Ok((get_service, set_service))
+    }
+
+    /// Returns a client for the specified service. If `persistent` is set the connection to the
+    /// service is kept open between calls.
+    pub fn service_client<S: ServicePair>(
+        &self,
+        service: &str,
+        persistent: bool,
+    ) -> Result<ServiceClient<S>, ServiceCallError> {
+        let service = self
+            .resolver
+            .translate(service)
+            .map_err(ServiceCallError::InvalidName)?;
+        Ok(ServiceClient::new(
+            self.master.clone(),
+            &self.name,
+            &service,
+            persistent,
+        ))
+    }
+
+    /// Waits until the specified service is registered with the master and its server accepts
+    /// connections, or until the `timeout` expires.
+    pub async fn wait_for_service(
+        &self,
+        service: &str,
+        timeout: Duration,
+    ) -> Result<(), ServiceCallError> {
+        let service = self
+            .resolver
+            .translate(service)
+            .map_err(ServiceCallError::InvalidName)?;
+        let service = service.as_str();
+        let wait = async {
+            loop {
+                match self.master.lookup_service(service).await {
+                    Ok(uri) => match probe_service(&uri, &self.name, service).await {
+                        Ok(()) => return,
+                        Err(e) => debug!(uri = uri.as_str(), "probing service failed: {}", e),
+                    },
+                    Err(e) => debug!("service lookup failed: {}", e),
+                }
+                tokio::time::delay_for(Duration::from_millis(100)).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .instrument(tracing::info_span!("wait_for_service", service = service))
+            .await
+            .map_err(|_| ServiceCallError::Timeout)
+    }
+}
+
+/// Returns a suffix that makes the name of the node unique, made from the process id and the
+/// current time like `rospy` does. A counter is added because several nodes can be created in the
+/// same process within the same millisecond.
+fn anonymous_suffix() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!(
+        "{}_{}_{}",
+        std::process::id(),
+        since_epoch.as_millis(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
 }
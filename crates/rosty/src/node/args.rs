@@ -1,3 +1,4 @@
+use serde::{Serialize, Serializer};
 use std::env;
 
 /// A builder helper class to construct a new ROS `Node`
@@ -7,6 +8,15 @@ pub struct NodeArgs {
     pub master_uri: String,
     pub hostname: String,
     pub namespace: String,
+
+    /// Pairs of names and the names they are replaced with
+    pub remappings: Vec<(String, String)>,
+
+    /// Whether a unique suffix is appended to the name of the node
+    pub anonymous: bool,
+
+    /// Names and values of private parameters that are set when the node is constructed
+    pub params: Vec<(String, String)>,
 }
 
 impl NodeArgs {
@@ -21,6 +31,9 @@ impl NodeArgs {
             master_uri: master_uri(),
             hostname: hostname(),
             namespace: ensure_starts_with_slash(namespace()),
+            remappings: remappings(),
+            anonymous: false,
+            params: params(),
         }
     }
 
@@ -47,6 +60,79 @@ impl NodeArgs {
         self.namespace = ensure_starts_with_slash(namespace.as_ref().to_owned());
         self
     }
+
+    /// Makes the name of the node unique by appending a suffix, so multiple instances of the same
+    /// node can run at the same time.
+    pub fn set_anonymous(mut self, anonymous: bool) -> NodeArgs {
+        self.anonymous = anonymous;
+        self
+    }
+
+    /// Adds a remapping that replaces the name `from` with `to`. Both names are resolved relative
+    /// to the node. Remappings added this way take precedence over remappings specified on the
+    /// command line.
+    pub fn add_remapping<S: AsRef<str>, T: AsRef<str>>(mut self, from: S, to: T) -> NodeArgs {
+        self.remappings
+            .push((from.as_ref().to_owned(), to.as_ref().to_owned()));
+        self
+    }
+
+    /// Adds a private parameter that is set when the node is constructed, like `_name:=value` on
+    /// the command line. The type of the value is inferred: integers, floating point numbers and
+    /// booleans are set as such, everything else is set as a string.
+    pub fn add_param<S: AsRef<str>, T: AsRef<str>>(mut self, name: S, value: T) -> NodeArgs {
+        self.params
+            .push((name.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+}
+
+/// The value of a private parameter that was specified as text
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ParamValue {
+    Int(i32),
+    Double(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl ParamValue {
+    /// Infers the type of a value like a YAML scalar. Quoted values are always strings.
+    pub fn parse(value: &str) -> ParamValue {
+        let quoted = value.len() >= 2
+            && ((value.starts_with('"') && value.ends_with('"'))
+                || (value.starts_with('\'') && value.ends_with('\'')));
+        if quoted {
+            return ParamValue::String(value[1..value.len() - 1].to_owned());
+        }
+        match value {
+            "true" | "True" | "TRUE" => return ParamValue::Bool(true),
+            "false" | "False" | "FALSE" => return ParamValue::Bool(false),
+            _ => {}
+        }
+        if let Ok(int) = value.parse() {
+            ParamValue::Int(int)
+        } else if let (true, Ok(double)) = (
+            // Rust also parses names like `inf` and `NaN` which YAML does not
+            value.chars().any(|c| c.is_ascii_digit()),
+            value.parse(),
+        ) {
+            ParamValue::Double(double)
+        } else {
+            ParamValue::String(value.to_owned())
+        }
+    }
+}
+
+impl Serialize for ParamValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ParamValue::Int(value) => serializer.serialize_i32(*value),
+            ParamValue::Double(value) => serializer.serialize_f64(*value),
+            ParamValue::Bool(value) => serializer.serialize_bool(*value),
+            ParamValue::String(value) => serializer.serialize_str(value),
+        }
+    }
 }
 
 /// Ensures that the specified string starts with a `/`
@@ -89,6 +175,39 @@ fn namespace() -> String {
         .unwrap_or_default()
 }
 
+/// Returns the remappings specified on the command line as `from:=to`. Special arguments
+/// (`__name:=`) and private parameters (`_param:=`) start with an underscore and are not remappings.
+fn remappings() -> Vec<(String, String)> {
+    env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('_'))
+        .filter_map(|arg| {
+            let mut parts = arg.splitn(2, ":=");
+            match (parts.next(), parts.next()) {
+                (Some(from), Some(to)) => Some((from.to_owned(), to.to_owned())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Returns the private parameters specified on the command line as `_name:=value`. Special
+/// arguments start with two underscores and are not parameters.
+fn params() -> Vec<(String, String)> {
+    env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("__"))
+        .filter_map(|arg| {
+            let arg = arg.strip_prefix('_')?;
+            let mut parts = arg.splitn(2, ":=");
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => Some((name.to_owned(), value.to_owned())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
 /// Helper function to find an argument with a given prefix
 fn find_arg_with_prefix(prefix: &str) -> Option<String> {
     env::args()
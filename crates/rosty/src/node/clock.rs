@@ -1,8 +1,47 @@
 use super::simtime::SimTime;
 use crate::node::{Node, SubscriptionError};
-use rosty_msg::Time;
+use futures::stream::{self, BoxStream};
+use futures::{future, Stream, StreamExt};
+use rosty_msg::{Duration, Time};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::SystemTime;
 
+/// A jump of the time of the node, for instance when a simulator or bag player restarts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockJump {
+    /// The time before the jump
+    pub previous: Time,
+
+    /// The time after the jump
+    pub current: Time,
+}
+
+impl ClockJump {
+    /// Returns true if the time jumped backwards
+    pub fn is_backwards(&self) -> bool {
+        self.current < self.previous
+    }
+
+    /// Returns the size of the jump, negative for backwards jumps
+    pub fn delta(&self) -> Duration {
+        self.current - self.previous
+    }
+}
+
+/// A stream of the jumps of the time of the node, see `Node::clock_jumps`
+pub struct ClockJumps {
+    inner: BoxStream<'static, ClockJump>,
+}
+
+impl Stream for ClockJumps {
+    type Item = ClockJump;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
 pub struct Clock {
     pub(crate) sim_time: Option<SimTime>,
 }
@@ -32,6 +71,72 @@ impl Clock {
         Ok(())
     }
 
+    /// Waits until `time` is reached. With simulated time this waits for clock messages, so the
+    /// wait pauses while the simulation is paused. Returns false if the time jumped backwards
+    /// before `time` was reached.
+    pub async fn sleep_until(&self, time: Time) -> bool {
+        match &self.sim_time {
+            Some(sim_time) => sim_time.wait_until(time).await,
+            None => {
+                if let Some(now) = self.now() {
+                    if now < time {
+                        let nanos = (time - now).nanos() as u64;
+                        tokio::time::delay_for(std::time::Duration::from_nanos(nanos)).await
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    /// Returns the number of times the time jumped backwards. Only simulated time is checked for
+    /// jumps.
+    pub fn backwards_jumps(&self) -> u64 {
+        self.sim_time
+            .as_ref()
+            .map_or(0, |sim_time| sim_time.backwards_jumps())
+    }
+
+    /// Returns a stream of the jumps of the time. Backwards jumps are always reported, forward
+    /// jumps only if they are larger than `forward_threshold`.
+    pub fn jumps(&self, forward_threshold: Option<Duration>) -> ClockJumps {
+        let updates = match &self.sim_time {
+            Some(sim_time) => sim_time.updates(),
+            None => {
+                return ClockJumps {
+                    inner: stream::pending().boxed(),
+                }
+            }
+        };
+        let inner = updates
+            .into_stream()
+            .filter_map(move |update| {
+                let jump = match update {
+                    Ok((previous, current)) => {
+                        let jump = ClockJump { previous, current };
+                        let forward =
+                            matches!(forward_threshold, Some(threshold) if jump.delta() > threshold);
+                        Some(jump).filter(|jump| jump.is_backwards() || forward)
+                    }
+                    Err(_) => None,
+                };
+                future::ready(jump)
+            })
+            .boxed();
+        ClockJumps { inner }
+    }
+
+    /// Returns the current time, waiting for the first clock message with simulated time
+    pub async fn wait_for_now(&self) -> Time {
+        match &self.sim_time {
+            Some(sim_time) => {
+                sim_time.wait_until(Time::new()).await;
+                sim_time.now().unwrap_or_default()
+            }
+            None => self.now().unwrap_or_default(),
+        }
+    }
+
     /// Returns true if the node is using simulated time
     pub fn is_using_sim_time(&self) -> bool {
         self.sim_time.is_some()
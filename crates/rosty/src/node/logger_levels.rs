@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::Subscriber;
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::reload;
+
+/// The name of the logger that stands for the default level, like the root logger of roscpp
+pub const ROOT_LOGGER: &str = "ros";
+
+/// The levels of the `tracing` targets (e.g. `rosty::tcpros`) that are enabled. A `LoggerLevels` is
+/// created together with a filter layer that is installed with the subscriber, after which the
+/// levels can be changed while the node runs. `Node::advertise_logger_services` makes them
+/// available through the `~get_loggers` and `~set_logger_level` services like roscpp does.
+pub struct LoggerLevels<S> {
+    levels: Arc<Mutex<Levels>>,
+    handle: reload::Handle<Targets, S>,
+}
+
+struct Levels {
+    default: LevelFilter,
+    targets: BTreeMap<String, LevelFilter>,
+}
+
+impl Levels {
+    fn filter(&self) -> Targets {
+        Targets::new()
+            .with_default(self.default)
+            .with_targets(self.targets.clone())
+    }
+}
+
+impl<S> Clone for LoggerLevels<S> {
+    fn clone(&self) -> Self {
+        LoggerLevels {
+            levels: self.levels.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<S: Subscriber + 'static> LoggerLevels<S> {
+    /// Returns a filter layer that enables the events at `default` level or above and the
+    /// `LoggerLevels` to change it with.
+    pub fn new(default: impl Into<LevelFilter>) -> (reload::Layer<Targets, S>, Self) {
+        Self::with_targets(default, Vec::<(String, LevelFilter)>::new())
+    }
+
+    /// Same as `new` but `targets` start out with a level of their own
+    pub fn with_targets<T, L>(
+        default: impl Into<LevelFilter>,
+        targets: impl IntoIterator<Item = (T, L)>,
+    ) -> (reload::Layer<Targets, S>, Self)
+    where
+        T: Into<String>,
+        L: Into<LevelFilter>,
+    {
+        let levels = Levels {
+            default: default.into(),
+            targets: targets
+                .into_iter()
+                .map(|(target, level)| (target.into(), level.into()))
+                .collect(),
+        };
+        let (layer, handle) = reload::Layer::new(levels.filter());
+        let levels = LoggerLevels {
+            levels: Arc::new(Mutex::new(levels)),
+            handle,
+        };
+        (layer, levels)
+    }
+
+    /// Returns the default level as `ROOT_LOGGER` followed by the targets that have a level of
+    /// their own
+    pub fn loggers(&self) -> Vec<(String, LevelFilter)> {
+        let levels = self.levels.lock().expect("logger levels are poisoned");
+        std::iter::once((ROOT_LOGGER.to_owned(), levels.default))
+            .chain(
+                levels
+                    .targets
+                    .iter()
+                    .map(|(target, level)| (target.clone(), *level)),
+            )
+            .collect()
+    }
+
+    /// Sets the level of the events of `target` and all targets within it. Setting the level of
+    /// `ROOT_LOGGER` changes the default level.
+    pub fn set_level(&self, target: &str, level: LevelFilter) -> Result<(), reload::Error> {
+        let mut levels = self.levels.lock().expect("logger levels are poisoned");
+        if target == ROOT_LOGGER {
+            levels.default = level;
+        } else {
+            levels.targets.insert(target.to_owned(), level);
+        }
+        self.handle.reload(levels.filter())
+    }
+}
+
+/// Parses the name of a level as used by roscpp, `fatal` maps to `error`
+pub(crate) fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.to_lowercase().as_str() {
+        "fatal" => Some(LevelFilter::ERROR),
+        level => level.parse().ok(),
+    }
+}
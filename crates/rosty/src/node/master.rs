@@ -1,5 +1,5 @@
 use crate::rosxmlrpc;
-use crate::rosxmlrpc::Response;
+use crate::rosxmlrpc::{Response, Value};
 use serde::{Deserialize, Serialize};
 
 /// Implements an API to communicate with the ROS master
@@ -81,6 +81,21 @@ impl Master {
             .await
     }
 
+    /// Subscribe the caller to updates of a parameter. Returns the current value of the parameter,
+    /// an empty struct if it is not set. Updates are received via the paramUpdate API.
+    pub async fn subscribe_param(&self, key: &str, caller_api: &str) -> Response<Value> {
+        self.client
+            .request_tree("subscribeParam", &(&self.client_id, caller_api, key))
+            .await
+    }
+
+    /// Unsubscribe the caller from updates of a parameter
+    pub async fn unsubscribe_param(&self, key: &str, caller_api: &str) -> Response<i32> {
+        self.client
+            .request("unsubscribeParam", &(&self.client_id, caller_api, key))
+            .await
+    }
+
     #[allow(dead_code)]
     pub async fn lookup_node(&self, node_name: &str) -> Response<String> {
         self.client
@@ -136,6 +151,54 @@ impl Master {
             .request("unregisterPublisher", &(&self.client_id, topic, caller_api))
             .await
     }
+
+    /// Register the caller as a provider of the specified service
+    pub async fn register_service(
+        &self,
+        service: &str,
+        service_api: &str,
+        caller_api: &str,
+    ) -> Response<i32> {
+        self.client
+            .request(
+                "registerService",
+                &(&self.client_id, service, service_api, caller_api),
+            )
+            .await
+    }
+
+    /// Unregister the caller as a provider of the specified service
+    pub async fn unregister_service(&self, service: &str, service_api: &str) -> Response<i32> {
+        self.client
+            .request(
+                "unregisterService",
+                &(&self.client_id, service, service_api),
+            )
+            .await
+    }
+
+    /// Retrieve the publishers, subscribers and services that are registered with the master
+    pub async fn get_system_state(&self) -> Response<SystemState> {
+        self.client
+            .request("getSystemState", &(&self.client_id))
+            .await
+            .map(
+                |(publishers, subscribers, services): (Registered, Registered, Registered)| {
+                    SystemState {
+                        publishers,
+                        subscribers,
+                        services,
+                    }
+                },
+            )
+    }
+
+    /// Lookup the `rosrpc://` URI of the node that provides the specified service
+    pub async fn lookup_service(&self, service: &str) -> Response<String> {
+        self.client
+            .request("lookupService", &(&self.client_id, service))
+            .await
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -143,3 +206,14 @@ pub struct Topic {
     pub name: String,
     pub data_type: String,
 }
+
+/// A list of topics or services together with the names of the nodes registered with each of them
+pub type Registered = Vec<(String, Vec<String>)>;
+
+/// The registrations known by the master
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SystemState {
+    pub publishers: Registered,
+    pub subscribers: Registered,
+    pub services: Registered,
+}
@@ -0,0 +1,112 @@
+//! Resolution of graph resource names. Names of topics and parameters can be specified in several
+//! forms which are resolved relative to the node:
+//!
+//! * `/global` names are used as is,
+//! * `relative` names are resolved in the namespace of the node,
+//! * `~private` names are resolved in the namespace of the node itself.
+//!
+//! After resolution the remappings of the node are applied.
+//!
+//! For more information read: https://wiki.ros.org/Names
+
+use std::collections::HashMap;
+
+/// The error returned for names that are not valid graph resource names
+#[derive(Debug, Clone, Eq, PartialEq, Fail)]
+#[fail(display = "invalid graph resource name '{}'", name)]
+pub struct NameError {
+    pub name: String,
+}
+
+/// Resolves names relative to a node and applies its remappings
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    /// The namespace of the node, without a trailing separator unless it is the root
+    namespace: String,
+
+    /// The global name of the node
+    name: String,
+
+    /// Replacements of resolved names
+    remappings: HashMap<String, String>,
+}
+
+impl Resolver {
+    /// Constructs a resolver for the node with the given global `name`. Both sides of the
+    /// `remappings` are resolved relative to the node, invalid remappings are ignored.
+    pub fn new(name: &str, remappings: &[(String, String)]) -> Self {
+        let namespace = match name.rfind('/') {
+            Some(0) | None => "/".to_owned(),
+            Some(index) => name[..index].to_owned(),
+        };
+        let mut resolver = Resolver {
+            namespace,
+            name: name.to_owned(),
+            remappings: HashMap::new(),
+        };
+        for (from, to) in remappings {
+            match (resolver.resolve(from), resolver.resolve(to)) {
+                (Ok(from), Ok(to)) => {
+                    resolver.remappings.insert(from, to);
+                }
+                (Err(e), _) | (_, Err(e)) => warn!("ignoring remapping {}:={}: {}", from, to, e),
+            }
+        }
+        resolver
+    }
+
+    /// Resolves a name to a global name without applying remappings
+    pub fn resolve(&self, name: &str) -> Result<String, NameError> {
+        if !is_legal_name(name) {
+            return Err(NameError {
+                name: name.to_owned(),
+            });
+        }
+        let name = if name.starts_with('/') {
+            name.to_owned()
+        } else if let Some(private) = name.strip_prefix('~') {
+            join(&self.name, private)
+        } else {
+            join(&self.namespace, name)
+        };
+        Ok(canonical(name))
+    }
+
+    /// Resolves a name to a global name and applies the remappings of the node
+    pub fn translate(&self, name: &str) -> Result<String, NameError> {
+        let name = self.resolve(name)?;
+        Ok(self.remappings.get(&name).cloned().unwrap_or(name))
+    }
+}
+
+/// Returns true if `name` is a legal graph resource name. Legal names start with a letter, `/` or
+/// `~` followed by letters, digits, underscores and separators. The empty name refers to the
+/// namespace of the node.
+fn is_legal_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first = match chars.next() {
+        Some(first) => first,
+        None => return true,
+    };
+    (first.is_ascii_alphabetic() || first == '/' || first == '~')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '/')
+        && !name.contains("//")
+}
+
+/// Joins a name to a namespace
+fn join(namespace: &str, name: &str) -> String {
+    format!(
+        "{}/{}",
+        namespace.trim_end_matches('/'),
+        name.trim_start_matches('/')
+    )
+}
+
+/// Returns a global name without a trailing separator
+fn canonical(name: String) -> String {
+    match name.trim_end_matches('/') {
+        "" => "/".to_owned(),
+        trimmed if trimmed.len() == name.len() => name,
+        trimmed => trimmed.to_owned(),
+    }
+}
@@ -0,0 +1,72 @@
+use super::slave::Slave;
+use crate::rosxmlrpc::{from_value, Response, Value};
+use futures::Stream;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// A stream of the values of a parameter. The current value of the parameter is received first,
+/// followed by every change. `None` is received when the parameter does not exist. While the
+/// parameter is watched, `Param::get` returns the locally cached value.
+pub struct ParamWatch<T> {
+    slave: Arc<Slave>,
+    key: String,
+    id: usize,
+    channel: mpsc::UnboundedReceiver<Value>,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> ParamWatch<T> {
+    pub(crate) async fn new(slave: Arc<Slave>, key: &str) -> Response<Self> {
+        let (id, channel) = slave.add_param_subscription(key).await?;
+        Ok(ParamWatch {
+            slave,
+            key: key.to_owned(),
+            id,
+            channel,
+            _type: PhantomData,
+        })
+    }
+}
+
+impl<T: DeserializeOwned> Stream for ParamWatch<T> {
+    type Item = Option<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let value = match this.channel.poll_recv(cx) {
+                Poll::Ready(Some(value)) => value,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            // The master signals a parameter that does not exist with an empty struct
+            if let Value::Struct(members) = &value {
+                if members.is_empty() {
+                    return Poll::Ready(Some(None));
+                }
+            }
+            match from_value(value) {
+                Ok(value) => return Poll::Ready(Some(Some(value))),
+                Err(e) => warn!(key = this.key.as_str(), "ignoring parameter update: {}", e),
+            }
+        }
+    }
+}
+
+impl<T> Drop for ParamWatch<T> {
+    fn drop(&mut self) {
+        let key = self.key.clone();
+        let id = self.id;
+        let slave = self.slave.clone();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                slave.remove_param_subscription(&key, id).await;
+            });
+        }
+    }
+}
@@ -1,28 +1,36 @@
 use crate::node::clock::Clock;
 use crate::node::slave::Slave;
-use crate::tcpros::{Message, PublisherError, PublisherSendError, PublisherStream};
+use crate::tcpros::{
+    ConnectionInfo, Message, MessageDescription, PublisherError, PublisherSendError,
+    PublisherStream, RawMessage, SubscriberEvent,
+};
 use failure::_core::sync::atomic::{AtomicUsize, Ordering};
+use futures::{future, Stream, StreamExt};
 use std::sync::Arc;
+use std::time::Duration;
 
+/// A publication of a topic. A `Publisher<RawMessage>` sends messages that are already serialized.
 #[derive(Clone)]
-pub struct Publisher<T: Message> {
+pub struct Publisher<T> {
     _info: Arc<PublisherInfo>,
     stream: PublisherStream<T>,
     clock: Arc<Clock>,
     seq: Arc<AtomicUsize>,
 }
 
-impl<T: Message> Publisher<T> {
+impl<T> Publisher<T> {
     pub(crate) async fn new(
         slave: Arc<Slave>,
         hostname: &str,
         topic: &str,
         queue_size: usize,
+        latching: bool,
+        description: MessageDescription,
         clock: Arc<Clock>,
     ) -> Result<Self, PublisherError> {
         // Register the subscription with the slave
         let stream = slave
-            .add_publication::<T>(hostname, topic, queue_size)
+            .add_publication::<T>(hostname, topic, queue_size, latching, description)
             .await?;
 
         Ok(Self {
@@ -36,6 +44,42 @@ impl<T: Message> Publisher<T> {
         })
     }
 
+    /// Returns the number of subscribers that are currently connected to this topic
+    pub fn num_subscribers(&self) -> usize {
+        self.stream.connections().count()
+    }
+
+    /// Returns a snapshot of the statistics of the connections to all subscribers
+    pub fn stats(&self) -> Vec<ConnectionInfo> {
+        self.stream.connections().snapshot()
+    }
+
+    /// Returns a stream of events that signal subscribers connecting to or disconnecting from the
+    /// topic. Only events that occur after calling this method are returned.
+    pub fn subscriber_events(&self) -> impl Stream<Item = SubscriberEvent> {
+        self.stream
+            .connections()
+            .events()
+            .filter_map(|event| future::ready(event.ok()))
+    }
+
+    /// Waits until at least `count` subscribers are connected to the topic. Returns false if the
+    /// `timeout` expired before that happened.
+    pub async fn wait_for_subscribers(&self, count: usize, timeout: Duration) -> bool {
+        let mut subscriber_count = self.stream.connections().watch_count();
+        let wait = async {
+            while *subscriber_count.borrow() < count {
+                if subscriber_count.recv().await.is_none() {
+                    return false;
+                }
+            }
+            true
+        };
+        tokio::time::timeout(timeout, wait).await.unwrap_or(false)
+    }
+}
+
+impl<T: Message> Publisher<T> {
     pub async fn send(&self, mut message: T) -> Result<(), PublisherSendError> {
         if let Some(header) = message.header_mut() {
             header.stamp = self.clock.now().expect("missing clock time");
@@ -45,6 +89,14 @@ impl<T: Message> Publisher<T> {
     }
 }
 
+impl Publisher<RawMessage> {
+    /// Sends a message that is already serialized, without its length prefix. The header of the
+    /// message is sent as is.
+    pub async fn send(&self, data: &[u8]) -> Result<(), PublisherSendError> {
+        self.stream.send(data).await
+    }
+}
+
 struct PublisherInfo {
     name: String,
     slave: Arc<Slave>,
@@ -0,0 +1,69 @@
+use crate::node::clock::Clock;
+use rosty_msg::{Duration, Time};
+use std::sync::Arc;
+
+/// Runs a loop at a fixed rate according to the clock of the node. With simulated time the rate
+/// follows the `/clock` topic, so the loop pauses when the simulation is paused and restarts when
+/// the time jumps backwards.
+pub struct Rate {
+    clock: Arc<Clock>,
+    period: Duration,
+    next: Option<Time>,
+    backwards_jumps: u64,
+}
+
+impl Rate {
+    /// # Panics
+    ///
+    /// Panics if `frequency` is not a finite positive number.
+    pub(crate) fn new(clock: Arc<Clock>, frequency: f64) -> Rate {
+        assert!(
+            frequency.is_finite() && frequency > 0.0,
+            "the frequency of a rate must be finite and positive, got {}",
+            frequency
+        );
+        Rate {
+            backwards_jumps: clock.backwards_jumps(),
+            clock,
+            period: Duration::from_nanos((1e9 / frequency) as i64),
+            next: None,
+        }
+    }
+
+    /// Returns the time between two cycles
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Restarts the rate, the next cycle ends one period after the next call to `sleep`
+    pub fn reset(&mut self) {
+        self.next = None;
+        self.backwards_jumps = self.clock.backwards_jumps();
+    }
+
+    /// Sleeps until the end of the current cycle. Returns the number of cycles that were missed
+    /// because the loop took longer than the period, in which case the rate restarts from now
+    /// without sleeping.
+    pub async fn sleep(&mut self) -> u64 {
+        let now = self.clock.wait_for_now().await;
+        if self.clock.backwards_jumps() != self.backwards_jumps {
+            self.reset();
+        }
+        let expected = self.next.unwrap_or(now + self.period);
+
+        if now > expected {
+            let period = self.period.nanos().max(1);
+            let late = (now - expected).nanos();
+            self.next = Some(now + self.period);
+            return ((late + period - 1) / period) as u64;
+        }
+
+        if self.clock.sleep_until(expected).await {
+            self.next = Some(expected + self.period);
+        } else {
+            // The time jumped backwards while sleeping
+            self.reset();
+        }
+        0
+    }
+}
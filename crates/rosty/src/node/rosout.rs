@@ -0,0 +1,129 @@
+use crate::node::clock::Clock;
+use crate::node::Node;
+use crate::tcpros::PublisherError;
+use rosty_msg::rosgraph_msgs::Log;
+use rosty_msg::std_msgs::Header;
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// A `tracing_subscriber::Layer` that publishes events as `rosgraph_msgs/Log` messages on
+/// `/rosout`, which makes them show up in tools like `rqt_console`. The messages are published by a
+/// background task from a bounded queue; when the queue is full events are dropped so logging never
+/// blocks. Events of the transport layer are not published, otherwise a warning about a message
+/// on `/rosout` would cause another message on `/rosout`.
+pub struct RosoutLayer {
+    name: String,
+    max_level: Level,
+    clock: Arc<Clock>,
+    sender: Mutex<mpsc::Sender<Log>>,
+}
+
+impl RosoutLayer {
+    /// Advertises `/rosout` for `node` and returns a layer that publishes on it, queueing at most
+    /// `queue_size` messages. By default only events at `INFO` level or above are published.
+    pub async fn new(node: &Node, queue_size: usize) -> Result<RosoutLayer, PublisherError> {
+        let publisher = node.publish_latched::<Log>("/rosout", queue_size).await?;
+        let (sender, mut receiver) = mpsc::channel::<Log>(queue_size.max(1));
+
+        let slave = node.slave.clone();
+        tokio::spawn(async move {
+            while let Some(mut log) = receiver.recv().await {
+                log.topics = slave.published_topics().await;
+                if publisher.send(log).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(RosoutLayer {
+            name: node.name().to_owned(),
+            max_level: Level::INFO,
+            clock: node.clock.clone(),
+            sender: Mutex::new(sender),
+        })
+    }
+
+    /// Sets the most verbose level of the events that are published
+    pub fn with_max_level(mut self, level: Level) -> Self {
+        self.max_level = level;
+        self
+    }
+}
+
+impl<S: Subscriber> Layer<S> for RosoutLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > self.max_level || is_transport_target(metadata.target()) {
+            return;
+        }
+
+        // Without a clock the message cannot be stamped
+        let stamp = match self.clock.now() {
+            Some(stamp) => stamp,
+            None => return,
+        };
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let log = Log {
+            header: Header {
+                stamp,
+                ..Default::default()
+            },
+            level: match *metadata.level() {
+                Level::ERROR => Log::ERROR,
+                Level::WARN => Log::WARN,
+                Level::INFO => Log::INFO,
+                Level::DEBUG | Level::TRACE => Log::DEBUG,
+            },
+            name: self.name.clone(),
+            msg: visitor.message + &visitor.fields,
+            file: metadata.file().unwrap_or_default().to_owned(),
+            function: metadata
+                .module_path()
+                .unwrap_or_else(|| metadata.target())
+                .to_owned(),
+            line: metadata.line().unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if let Ok(mut sender) = self.sender.lock() {
+            let _ = sender.try_send(log);
+        }
+    }
+}
+
+/// Returns true if events with the given `target` originate from the transport layer
+fn is_transport_target(target: &str) -> bool {
+    target == "rosty::tcpros" || target.starts_with("rosty::tcpros::")
+}
+
+/// Formats the fields of an event as the message followed by `name=value` pairs
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
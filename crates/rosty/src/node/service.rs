@@ -0,0 +1,52 @@
+use crate::node::slave::Slave;
+use crate::tcpros::{ServiceError, ServicePair};
+use std::future::Future;
+use std::sync::Arc;
+
+/// A service advertised by this node. The service is unregistered from the master when this
+/// handle is dropped.
+pub struct Service {
+    slave: Arc<Slave>,
+    name: String,
+}
+
+impl Service {
+    pub(crate) async fn new<S, F, R>(
+        slave: Arc<Slave>,
+        hostname: &str,
+        name: &str,
+        handler: F,
+    ) -> Result<Self, ServiceError>
+    where
+        S: ServicePair,
+        F: Fn(S::Request) -> R + Send + Sync + 'static,
+        R: Future<Output = Result<S::Response, String>> + Send + 'static,
+    {
+        // Register the service with the slave
+        slave
+            .add_service::<S, _, _>(hostname, name, handler)
+            .await?;
+
+        Ok(Self {
+            slave,
+            name: name.to_owned(),
+        })
+    }
+
+    /// Returns the name of the service
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        let name = self.name.clone();
+        let slave = self.slave.clone();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                slave.remove_service(&name).await;
+            });
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::node::master::Master;
+use crate::tcpros::{ServiceCallError, ServiceConnection, ServicePair};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A client to call a service provided by another node. The location of the service is resolved
+/// through the master on every connection attempt.
+pub struct ServiceClient<S: ServicePair> {
+    master: Arc<Master>,
+    caller_id: String,
+    service: String,
+    persistent: bool,
+    connection: Mutex<Option<ServiceConnection>>,
+    datatype: PhantomData<S>,
+}
+
+impl<S: ServicePair> ServiceClient<S> {
+    pub(crate) fn new(
+        master: Arc<Master>,
+        caller_id: &str,
+        service: &str,
+        persistent: bool,
+    ) -> Self {
+        ServiceClient {
+            master,
+            caller_id: caller_id.to_owned(),
+            service: service.to_owned(),
+            persistent,
+            connection: Mutex::new(None),
+            datatype: PhantomData,
+        }
+    }
+
+    /// Returns the name of the service
+    pub fn name(&self) -> &str {
+        &self.service
+    }
+
+    /// Returns true if the connection to the service is kept open between calls
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    /// Calls the service with the given `request`. If the server fails to handle the request the
+    /// error message of the server is returned as `ServiceCallError::ServiceFailure`.
+    ///
+    /// Persistent clients reuse their connection. If that connection turns out to be closed before
+    /// the request is sent, the service is looked up again and the request is sent on a new
+    /// connection. Once a request was sent it is never sent again, so a handler does not run twice.
+    pub async fn call(&self, request: &S::Request) -> Result<S::Response, ServiceCallError> {
+        if !self.persistent {
+            return self.connect().await?.call::<S>(request).await;
+        }
+
+        let mut connection = self.connection.lock().await;
+        let mut existing = connection.take();
+        if let Some(previous) = existing.as_mut() {
+            if previous.is_closed().await {
+                warn!(
+                    service = self.service.as_str(),
+                    "persistent connection was closed, reconnecting.."
+                );
+                existing = None;
+            }
+        }
+
+        let sent = match existing {
+            Some(mut existing) => match existing.send::<S>(request).await {
+                Ok(()) => Some(existing),
+                Err(e) => {
+                    warn!(
+                        service = self.service.as_str(),
+                        "persistent connection lost: {}, reconnecting..", e
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut sent = match sent {
+            Some(sent) => sent,
+            None => {
+                let mut new_connection = self.connect().await?;
+                new_connection.send::<S>(request).await?;
+                new_connection
+            }
+        };
+
+        // Keep the connection unless it broke while waiting for the response
+        let result = sent.receive::<S>().await;
+        if !matches!(result, Err(ServiceCallError::TransportError(_))) {
+            *connection = Some(sent);
+        }
+        result
+    }
+
+    /// Looks up the service with the master and connects to it
+    async fn connect(&self) -> Result<ServiceConnection, ServiceCallError> {
+        let uri = self
+            .master
+            .lookup_service(&self.service)
+            .await
+            .map_err(ServiceCallError::LookupError)?;
+        ServiceConnection::connect::<S>(&uri, &self.caller_id, &self.service, self.persistent).await
+    }
+}
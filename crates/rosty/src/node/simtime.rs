@@ -2,32 +2,60 @@ use crate::node::{Node, SubscriptionError};
 
 use futures::StreamExt;
 use rosty_msg::Time;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use tokio::sync::{broadcast, watch};
 
 #[derive(Debug)]
 /// The Simulated Time struct, holds a reference to the last message received on the clock interface
 pub struct SimTime {
     last_clock_msg: Arc<RwLock<Option<Time>>>,
+    changes: watch::Receiver<Option<Time>>,
+    sender: Arc<watch::Sender<Option<Time>>>,
+    backwards_jumps: Arc<AtomicU64>,
+    updates: broadcast::Sender<(Time, Time)>,
 }
 
 impl SimTime {
     pub fn new() -> Self {
         let last_clock_msg = Arc::new(RwLock::new(None));
-        SimTime { last_clock_msg }
+        let (sender, changes) = watch::channel(None);
+        let (updates, _) = broadcast::channel(64);
+        SimTime {
+            last_clock_msg,
+            changes,
+            sender: Arc::new(sender),
+            backwards_jumps: Arc::new(AtomicU64::new(0)),
+            updates,
+        }
     }
 
     /// Initialize to the subscribing of simulated time
     pub async fn init(&self, node: &Node) -> Result<(), SubscriptionError> {
         let last_clock_msg_cloned = self.last_clock_msg.clone();
+        let sender = self.sender.clone();
+        let backwards_jumps = self.backwards_jumps.clone();
+        let updates = self.updates.clone();
         let future = node
             .subscribe::<rosty_msg::rosgraph_msgs::Clock>("/clock", 1)
             .await?
             .for_each(move |(_, clock_msg)| {
                 let local = last_clock_msg_cloned.clone();
+                let sender = sender.clone();
+                let backwards_jumps = backwards_jumps.clone();
+                let updates = updates.clone();
                 // Set the last variable as a member
                 async move {
-                    let mut guard = local.write().unwrap();
-                    *guard = Some(clock_msg.clock);
+                    let time = clock_msg.clock;
+                    let previous = local.write().unwrap().replace(time);
+                    if let Some(previous) = previous {
+                        // Waiters are woken below and check the number of jumps
+                        if time < previous {
+                            backwards_jumps.fetch_add(1, Ordering::SeqCst);
+                        }
+                        let _ = updates.send((previous, time));
+                    }
+                    let _ = sender.broadcast(Some(time));
                 }
             });
 
@@ -39,4 +67,28 @@ impl SimTime {
     pub fn now(&self) -> Option<Time> {
         self.last_clock_msg.read().unwrap().map(Into::into)
     }
+
+    /// Returns the number of times the simulated time jumped backwards
+    pub fn backwards_jumps(&self) -> u64 {
+        self.backwards_jumps.load(Ordering::SeqCst)
+    }
+
+    /// Returns a receiver of the previous and the new time of every change of the simulated time
+    pub fn updates(&self) -> broadcast::Receiver<(Time, Time)> {
+        self.updates.subscribe()
+    }
+
+    /// Waits until the simulated time reaches `time`. While no clock messages are received the
+    /// simulated time does not advance. Returns false if the simulated time jumped backwards
+    /// before `time` was reached.
+    pub async fn wait_until(&self, time: Time) -> bool {
+        let jumps = self.backwards_jumps();
+        let mut changes = self.changes.clone();
+        while !matches!(self.now(), Some(now) if now >= time) {
+            if self.backwards_jumps() != jumps || changes.recv().await.is_none() {
+                return false;
+            }
+        }
+        true
+    }
 }
@@ -2,19 +2,28 @@ use nix::unistd::getpid;
 
 use crate::node::error::SubscriptionError;
 use crate::node::master::Master;
+use crate::node::slave::params_tracker::ParamsTracker;
 use crate::node::slave::publications_tracker::PublicationsTracker;
+use crate::node::slave::services_tracker::ServicesTracker;
 use crate::node::slave::subscriptions_tracker::SubscriptionsTracker;
 use crate::rosxmlrpc::{Params, Response, ResponseError, ServerBuilder, Value};
-use crate::shutdown_token::ShutdownToken;
-use crate::tcpros::{IncomingMessage, Message, PublisherError, PublisherStream};
+use crate::shutdown_token::{ShutdownReason, ShutdownToken};
+use crate::tcpros::{
+    ConnectionInfo, Direction, IncomingMessage, MessageDescription, PublisherError,
+    PublisherStream, ServiceError, ServicePair, SubscriberMessage,
+};
+use crate::Topic;
 use futures::future::TryFutureExt;
 use futures::StreamExt;
+use std::convert::TryInto;
 use std::future::Future;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing_futures::Instrument;
 
+mod params_tracker;
 mod publications_tracker;
+mod services_tracker;
 mod subscriptions_tracker;
 
 fn unwrap_array_case(params: Params) -> Params {
@@ -32,6 +41,8 @@ pub struct Slave {
     master: Arc<Master>,
     subscriptions: Arc<SubscriptionsTracker>,
     publications: Arc<PublicationsTracker>,
+    services: Arc<ServicesTracker>,
+    params: Arc<ParamsTracker>,
 }
 
 impl Slave {
@@ -47,6 +58,8 @@ impl Slave {
     ) -> Result<(Slave, impl Future<Output = Result<(), failure::Error>>), failure::Error> {
         let subscriptions = Arc::new(SubscriptionsTracker::default());
         let publications = Arc::new(PublicationsTracker::default());
+        let services = Arc::new(ServicesTracker::default());
+        let params = Arc::new(ParamsTracker::default());
 
         // Resolve the hostname to an address. 0 for the port indicates that the slave can bind to
         // any port that is available
@@ -68,11 +81,62 @@ impl Slave {
             Ok(Value::Int(getpid().into()))
         });
 
-        let name_string = String::from(name);
+        let subs = subscriptions.clone();
+        let pubs = publications.clone();
+        server.register_value("getBusInfo", "Bus info", move |_args| {
+            let subs = subs.clone();
+            let pubs = pubs.clone();
+            async move {
+                let mut connections = subs.connections().await;
+                connections.extend(pubs.connections().await);
+                Ok(Value::Array(
+                    connections.into_iter().map(bus_info).collect(),
+                ))
+            }
+        });
+
+        let subs = subscriptions.clone();
+        let pubs = publications.clone();
+        server.register_value("getBusStats", "Bus stats", move |_args| {
+            let subs = subs.clone();
+            let pubs = pubs.clone();
+            async move {
+                let publish_stats = pubs
+                    .connections_by_topic()
+                    .await
+                    .into_iter()
+                    .map(|(topic, connections)| publish_stats(topic, connections))
+                    .collect();
+                let subscribe_stats = subs
+                    .connections_by_topic()
+                    .await
+                    .into_iter()
+                    .map(|(topic, connections)| subscribe_stats(topic, connections))
+                    .collect();
+                Ok(Value::Array(vec![
+                    Value::Array(publish_stats),
+                    Value::Array(subscribe_stats),
+                    // Statistics of services are not tracked
+                    Value::Array(vec![]),
+                ]))
+            }
+        });
+
+        let subs = subscriptions.clone();
+        server.register_value("getSubscriptions", "List of subscriptions", move |_args| {
+            let subs = subs.clone();
+            async move { Ok(topic_list(subs.topics().await)) }
+        });
+
+        let pubs = publications.clone();
+        server.register_value("getPublications", "List of publications", move |_args| {
+            let pubs = pubs.clone();
+            async move { Ok(topic_list(pubs.topics().await)) }
+        });
+
         let subs = subscriptions.clone();
         server.register_value("publisherUpdate", "Publishers updated", move |args| {
             let subs = subs.clone();
-            let name_string = name_string.clone();
             async move {
                 let mut args = unwrap_array_case(args).into_iter();
                 let caller_id = match args
@@ -93,12 +157,29 @@ impl Slave {
                     _ => Err(ResponseError::Client("publishers need to be strings".to_owned()))
                 })
                     .collect::<Response<Vec<String>>>()?;
-                subs.add_publishers(&topic, &name_string, publishers.iter().cloned())
+                subs.set_publishers(&topic, publishers.iter().cloned())
                     .instrument(tracing::trace_span!("publisherUpdate", caller_id=caller_id.as_str(), topic=topic.as_str(), publishers=?publishers))
-                    .await
-                    .map_err(|v| {
-                        ResponseError::Server(format!("failed to handle publishers: {}", v))
-                    })?;
+                    .await;
+                Ok(Value::Int(0))
+            }
+        });
+
+        let prms = params.clone();
+        server.register_value("paramUpdate", "Parameter updated", move |args| {
+            let prms = prms.clone();
+            async move {
+                let mut args = unwrap_array_case(args).into_iter();
+                let _caller_id = args
+                    .next()
+                    .ok_or_else(|| ResponseError::Client("missing argument 'caller_id'".into()))?;
+                let key = match args.next() {
+                    Some(Value::String(key)) => key,
+                    _ => return Err(ResponseError::Client("missing argument 'key'".into())),
+                };
+                let value = args
+                    .next()
+                    .ok_or_else(|| ResponseError::Client("missing argument 'value'".into()))?;
+                prms.update(canonical_param_key(&key), value).await;
                 Ok(Value::Int(0))
             }
         });
@@ -167,7 +248,7 @@ impl Slave {
                     _ => return Err(ResponseError::Client("Missing argument 'message'".into())),
                 };
                 info!("server is shutting down because: {}", message);
-                shutdown_signal.shutdown();
+                shutdown_signal.shutdown_with_reason(ShutdownReason::Remote(message));
                 Ok(Value::Int(0))
             }
         });
@@ -179,6 +260,8 @@ impl Slave {
         // Create a future that awaits the server shutdown and then performs cleanup
         let subs = subscriptions.clone();
         let pubs = publications.clone();
+        let srvs = services.clone();
+        let prms = params.clone();
         let master_clone = master.clone();
         let caller_api = uri.clone();
         let server = tokio::spawn(async move {
@@ -201,6 +284,16 @@ impl Slave {
                 })
                 .await;
 
+            futures::stream::iter(srvs.remove_all().await.iter())
+                .for_each_concurrent(None, |(service, service_api)| {
+                    unregister_service(master, service, service_api)
+                })
+                .await;
+
+            futures::stream::iter(prms.remove_all().await.iter())
+                .for_each_concurrent(None, |key| unsubscribe_param(master, key, &caller_api))
+                .await;
+
             Ok(())
         })
         .unwrap_or_else(|e| Err(e.into()));
@@ -212,6 +305,8 @@ impl Slave {
                 master,
                 subscriptions,
                 publications,
+                services,
+                params,
             },
             server,
         ))
@@ -222,78 +317,120 @@ impl Slave {
         &self.uri
     }
 
-    /// Adds a new subscription to list of tracked subscriptions
-    pub async fn add_subscription<T: Message>(
+    /// Adds a new subscription to list of tracked subscriptions. Subscriptions to the same topic
+    /// share their connections, only the first one is registered with the master.
+    pub async fn add_subscription<T: SubscriberMessage>(
         &self,
         topic: &str,
         queue_size: usize,
-    ) -> Result<mpsc::Receiver<IncomingMessage<T>>, SubscriptionError> {
-        // Add the subscriptions to the list of subscribers
-        let receiver = self
-            .subscriptions
-            .add(&self.name, topic, queue_size)
-            .await?;
-
-        // Notify the master that we are subscribing to the given topic. The master will return
-        // a list of publishers that publish to the topic we want to subscribe to.
-        let publishers = self
-            .master
-            .register_subscriber(topic, &T::msg_type(), self.uri())
+    ) -> Result<(usize, mpsc::Receiver<IncomingMessage<T>>), SubscriptionError> {
+        // The first subscription notifies the master that we are subscribing to the given topic.
+        // The master will return a list of publishers that publish to the topic, the slave then
+        // connects to them to receive the data.
+        let master = &self.master;
+        let uri = self.uri();
+        self.subscriptions
+            .add::<T, _, _>(&self.name, topic, queue_size, || async move {
+                let publishers = master
+                    .register_subscriber(topic, T::description().msg_type(), uri)
+                    .await
+                    .map_err(SubscriptionError::MasterCommunicationError)?;
+                info!(topic = topic, "successfully registered subscriber");
+                Ok(publishers)
+            })
             .await
-            .map_err(SubscriptionError::MasterCommunicationError)?;
+    }
+
+    /// Subscribes to updates of the parameter with the given global `key`. Parameters are only
+    /// subscribed to with the master once. Returns the id of the receiver and a channel that
+    /// receives the current value of the parameter followed by all updates.
+    pub async fn add_param_subscription(
+        &self,
+        key: &str,
+    ) -> Response<(usize, mpsc::UnboundedReceiver<Value>)> {
+        let (id, receiver, is_new) = self.params.add(key).await;
+        if is_new {
+            match self.master.subscribe_param(key, self.uri()).await {
+                Ok(value) => self.params.initialize(key, value).await,
+                Err(e) => {
+                    self.params.remove(key, id).await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok((id, receiver))
+    }
 
-        info!(topic = topic, "successfully registered subscriber");
+    /// Removes the specified parameter subscription. The master is notified when the last
+    /// subscription to the parameter is removed.
+    pub async fn remove_param_subscription(&self, key: &str, id: usize) {
+        if self.params.remove(key, id).await {
+            unsubscribe_param(&self.master, key, self.uri()).await
+        }
+    }
 
-        // Let the slave know which nodes are publishing data for the topic so that the slave will
-        // connect to them to receive the data
-        self.add_publishers_to_subscription(topic, publishers.into_iter())
-            .await?;
+    /// Returns the cached value of the parameter with the given global `key` if it is subscribed
+    /// to.
+    pub async fn cached_param(&self, key: &str) -> Option<Value> {
+        self.params.get(key).await
+    }
+
+    /// Forgets the cached values of the parameters affected by a change of the parameter with the
+    /// given global `key`.
+    pub async fn invalidate_cached_params(&self, key: &str) {
+        self.params.invalidate(key).await
+    }
+
+    /// Returns the links of the subscription to the specified topic
+    pub async fn subscription_connections(&self, topic: &str) -> Vec<ConnectionInfo> {
+        self.subscriptions.topic_connections(topic).await
+    }
 
-        Ok(receiver)
+    /// Returns the names of the topics that are published
+    pub async fn published_topics(&self) -> Vec<String> {
+        self.publications
+            .topics()
+            .await
+            .into_iter()
+            .map(|topic| topic.name)
+            .collect()
     }
 
-    /// Removes the specified subscription
-    pub async fn remove_subscription(&self, topic: &str) {
+    /// Removes the specified subscription. The master is notified when the last subscription to
+    /// the topic is removed.
+    pub async fn remove_subscription(&self, topic: &str, id: usize) {
         // Remove the subscription from the list of subscriptions
-        if self.subscriptions.remove(topic).await {
+        if self.subscriptions.remove(topic, id).await {
             // Notify the master about the unsubscription
             unregister_subscriber(&self.master, topic, self.uri()).await
         }
     }
 
-    /// Tell the slave that the specified `publishers` publish data to the given topic. The slave
-    /// will try to connect to the publishers.
-    async fn add_publishers_to_subscription<T>(
-        &self,
-        topic: &str,
-        publishers: T,
-    ) -> Result<(), SubscriptionError>
-    where
-        T: Iterator<Item = String>,
-    {
-        self.subscriptions
-            .add_publishers(topic, &self.name, publishers)
-            .await
-    }
-
     pub async fn add_publication<T>(
         &self,
         hostname: &str,
         topic: &str,
         queue_size: usize,
-    ) -> Result<PublisherStream<T>, PublisherError>
-    where
-        T: Message,
-    {
+        latching: bool,
+        description: MessageDescription,
+    ) -> Result<PublisherStream<T>, PublisherError> {
         // Create the publisher object to be able to actually publish data
+        let data_type = description.msg_type().to_owned();
         let publisher = self
             .publications
-            .add(hostname, topic, queue_size, &self.name)
+            .add(
+                hostname,
+                topic,
+                queue_size,
+                &self.name,
+                latching,
+                description,
+            )
             .await?;
 
         // Register the publisher with the master
         self.master
-            .register_publisher(topic, &T::msg_type(), &self.uri)
+            .register_publisher(topic, &data_type, &self.uri)
             .await
             .map_err(PublisherError::RegistrationError)?;
 
@@ -310,6 +447,48 @@ impl Slave {
             unregister_publisher(&self.master, topic, self.uri()).await
         }
     }
+
+    /// Starts serving the specified service and registers it with the master
+    pub async fn add_service<S, F, R>(
+        &self,
+        hostname: &str,
+        service: &str,
+        handler: F,
+    ) -> Result<(), ServiceError>
+    where
+        S: ServicePair,
+        F: Fn(S::Request) -> R + Send + Sync + 'static,
+        R: Future<Output = Result<S::Response, String>> + Send + 'static,
+    {
+        // Start the server that will handle the service requests
+        let service_api = self
+            .services
+            .add::<S, _, _>(hostname, service, &self.name, handler)
+            .await?;
+
+        // Register the service with the master
+        if let Err(e) = self
+            .master
+            .register_service(service, &service_api, &self.uri)
+            .await
+        {
+            self.services.remove(service).await;
+            return Err(ServiceError::RegistrationError(e));
+        }
+
+        info!(service = service, "successfully registered service");
+
+        Ok(())
+    }
+
+    /// Removes the specified service
+    pub async fn remove_service(&self, service: &str) {
+        // Remove the service from the list of services
+        if let Some(service_api) = self.services.remove(service).await {
+            // Notify the master that the service is no longer available
+            unregister_service(&self.master, service, &service_api).await
+        }
+    }
 }
 
 /// Unregister the given topic from the master and report on it
@@ -333,3 +512,113 @@ async fn unregister_publisher(master: &Master, topic: &str, caller_api: &str) {
         _ => info!(topic = topic, "successfully unregistered publisher"),
     };
 }
+
+/// Unsubscribe from updates of the given parameter and report on it
+async fn unsubscribe_param(master: &Master, key: &str, caller_api: &str) {
+    match master.unsubscribe_param(key, caller_api).await {
+        Err(e) => error!(key = key, "error unsubscribing from parameter: {}", e),
+        _ => info!(key = key, "successfully unsubscribed from parameter"),
+    };
+}
+
+/// Returns the key of a parameter without a trailing separator. Masters may send updates for a
+/// key with a trailing separator.
+fn canonical_param_key(key: &str) -> &str {
+    match key.trim_end_matches('/') {
+        "" => "/",
+        key => key,
+    }
+}
+
+/// Unregister the given service from the master and report on it
+async fn unregister_service(master: &Master, service: &str, service_api: &str) {
+    match master.unregister_service(service, service_api).await {
+        Err(e) => error!(
+            service = service,
+            "error unregistering service from master: {}", e
+        ),
+        _ => info!(service = service, "successfully unregistered service"),
+    };
+}
+
+/// Encodes a connection as it is returned by `getBusInfo`:
+/// `[connectionId, destinationId, direction, transport, topic, connected]`
+fn bus_info(connection: ConnectionInfo) -> Value {
+    let direction = match connection.direction {
+        Direction::Inbound => "i",
+        Direction::Outbound => "o",
+    };
+    Value::Array(vec![
+        saturating_int(connection.id),
+        Value::String(connection.destination),
+        Value::String(direction.to_owned()),
+        Value::String("TCPROS".to_owned()),
+        Value::String(connection.topic),
+        Value::Bool(connection.connected),
+    ])
+}
+
+/// Encodes the statistics of a published topic as it is returned by `getBusStats`:
+/// `[topic, messageDataSent, [[connectionId, bytesSent, numSentMessages, connected]...]]`
+fn publish_stats(topic: String, connections: Vec<ConnectionInfo>) -> Value {
+    let bytes_sent: u64 = connections.iter().map(|c| c.stats.bytes).sum();
+    Value::Array(vec![
+        Value::String(topic),
+        saturating_int(bytes_sent),
+        Value::Array(
+            connections
+                .into_iter()
+                .map(|connection| {
+                    Value::Array(vec![
+                        saturating_int(connection.id),
+                        saturating_int(connection.stats.bytes),
+                        saturating_int(connection.stats.messages),
+                        Value::Bool(connection.connected),
+                    ])
+                })
+                .collect(),
+        ),
+    ])
+}
+
+/// Encodes the statistics of a subscribed topic as it is returned by `getBusStats`:
+/// `[topic, [[connectionId, bytesReceived, numReceivedMessages, drops, connected]...]]`
+fn subscribe_stats(topic: String, connections: Vec<ConnectionInfo>) -> Value {
+    Value::Array(vec![
+        Value::String(topic),
+        Value::Array(
+            connections
+                .into_iter()
+                .map(|connection| {
+                    Value::Array(vec![
+                        saturating_int(connection.id),
+                        saturating_int(connection.stats.bytes),
+                        saturating_int(connection.stats.messages),
+                        saturating_int(connection.stats.drops),
+                        Value::Bool(connection.connected),
+                    ])
+                })
+                .collect(),
+        ),
+    ])
+}
+
+/// Encodes a counter as an XML-RPC integer, counters beyond `i32::MAX` are reported as `i32::MAX`
+fn saturating_int(value: impl TryInto<i32>) -> Value {
+    Value::Int(value.try_into().unwrap_or(i32::MAX))
+}
+
+/// Encodes a list of topics as `[[topic, type]...]`
+fn topic_list(topics: Vec<Topic>) -> Value {
+    Value::Array(
+        topics
+            .into_iter()
+            .map(|topic| {
+                Value::Array(vec![
+                    Value::String(topic.name),
+                    Value::String(topic.data_type),
+                ])
+            })
+            .collect(),
+    )
+}
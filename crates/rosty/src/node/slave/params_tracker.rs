@@ -0,0 +1,127 @@
+use crate::rosxmlrpc::Value;
+use std::collections::HashMap;
+use tokio::sync::{mpsc, Mutex};
+
+/// The subscription to updates of a single parameter
+struct ParamSubscription {
+    /// The last known value of the parameter, `None` until the master sent it
+    value: Option<Value>,
+    /// The number of updates and invalidations since the subscription was created
+    version: usize,
+    next_id: usize,
+    senders: Vec<(usize, mpsc::UnboundedSender<Value>)>,
+}
+
+impl ParamSubscription {
+    /// Stores `value` and sends it to all receivers if it changed
+    fn set_value(&mut self, value: Value) {
+        if self.value.as_ref() == Some(&value) {
+            return;
+        }
+        self.senders
+            .retain(|(_, sender)| sender.send(value.clone()).is_ok());
+        self.value = Some(value);
+    }
+}
+
+/// Keeps track of the parameters that are subscribed to and caches their values
+#[derive(Default)]
+pub struct ParamsTracker {
+    mapping: Mutex<HashMap<String, ParamSubscription>>,
+}
+
+impl ParamsTracker {
+    /// Adds a receiver of the updates of the specified parameter. If the value of the parameter is
+    /// already known it is sent to the receiver immediately. Returns the id of the receiver, its
+    /// channel and whether the subscription was newly created.
+    pub async fn add(&self, key: &str) -> (usize, mpsc::UnboundedReceiver<Value>, bool) {
+        let mut mapping = self.mapping.lock().await;
+        let is_new = !mapping.contains_key(key);
+        let subscription = mapping
+            .entry(key.to_owned())
+            .or_insert_with(|| ParamSubscription {
+                value: None,
+                version: 0,
+                next_id: 0,
+                senders: Vec::new(),
+            });
+        let (sender, receiver) = mpsc::unbounded_channel();
+        if let Some(value) = &subscription.value {
+            let _ = sender.send(value.clone());
+        }
+        let id = subscription.next_id;
+        subscription.next_id += 1;
+        subscription.senders.push((id, sender));
+        (id, receiver, is_new)
+    }
+
+    /// Stores the new value of a subscribed parameter and sends it to all receivers if it changed.
+    /// Updates of parameters that are not subscribed to are ignored.
+    pub async fn update(&self, key: &str, value: Value) {
+        if let Some(subscription) = self.mapping.lock().await.get_mut(key) {
+            subscription.version += 1;
+            subscription.set_value(value);
+        }
+    }
+
+    /// Stores the value the master returned when the parameter was subscribed to. The value is
+    /// ignored if an update or invalidation arrived in the meantime, because it is older.
+    pub async fn initialize(&self, key: &str, value: Value) {
+        if let Some(subscription) = self.mapping.lock().await.get_mut(key) {
+            if subscription.version == 0 {
+                subscription.set_value(value);
+            }
+        }
+    }
+
+    /// Forgets the cached values of all subscribed parameters that are affected by a change of the
+    /// parameter `key`, until the master sends their new values.
+    pub async fn invalidate(&self, key: &str) {
+        for (subscribed, subscription) in self.mapping.lock().await.iter_mut() {
+            if contains(key, subscribed) || contains(subscribed, key) {
+                subscription.version += 1;
+                subscription.value = None;
+            }
+        }
+    }
+
+    /// Returns the cached value of a subscribed parameter
+    pub async fn get(&self, key: &str) -> Option<Value> {
+        self.mapping
+            .lock()
+            .await
+            .get(key)
+            .and_then(|subscription| subscription.value.clone())
+    }
+
+    /// Removes the receiver with the given id. Returns true if this was the last receiver, in which
+    /// case the subscription itself is removed.
+    pub async fn remove(&self, key: &str, id: usize) -> bool {
+        let mut mapping = self.mapping.lock().await;
+        let is_empty = match mapping.get_mut(key) {
+            Some(subscription) => {
+                subscription
+                    .senders
+                    .retain(|(receiver_id, _)| *receiver_id != id);
+                subscription.senders.is_empty()
+            }
+            None => return false,
+        };
+        if is_empty {
+            mapping.remove(key);
+        }
+        is_empty
+    }
+
+    /// Removes all the subscriptions and returns the keys of the parameters that were released.
+    pub async fn remove_all(&self) -> Vec<String> {
+        self.mapping.lock().await.drain().map(|(k, _)| k).collect()
+    }
+}
+
+/// Returns true if the parameter `key` is within the namespace `namespace` or equal to it
+fn contains(namespace: &str, key: &str) -> bool {
+    namespace == "/"
+        || key == namespace
+        || (key.starts_with(namespace) && key[namespace.len()..].starts_with('/'))
+}
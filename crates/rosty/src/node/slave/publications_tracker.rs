@@ -1,4 +1,7 @@
-use crate::tcpros::{Message, Publisher, PublisherError, PublisherStream};
+use crate::tcpros::{
+    ConnectionInfo, MessageDescription, Publisher, PublisherError, PublisherStream,
+};
+use crate::Topic;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use tokio::sync::Mutex;
@@ -9,24 +12,41 @@ pub struct PublicationsTracker {
 }
 
 impl PublicationsTracker {
-    pub async fn add<T: Message>(
+    /// Returns a stream to publish messages of the given `description` to the specified topic.
+    /// Publications of the same topic share their publisher.
+    pub async fn add<T>(
         &self,
         hostname: &str,
         topic: &str,
         queue_size: usize,
         caller_id: &str,
+        latching: bool,
+        description: MessageDescription,
     ) -> Result<PublisherStream<T>, PublisherError> {
         match self.mapping.lock().await.entry(topic.to_owned()) {
-            Entry::Occupied(entry) => entry.get().stream::<T>(queue_size),
+            Entry::Occupied(entry) => {
+                if entry.get().is_latching() != latching {
+                    warn!(
+                        topic = topic,
+                        "topic is already published with latching {}",
+                        if latching { "disabled" } else { "enabled" }
+                    );
+                }
+                entry.get().stream::<T>(queue_size, &description)
+            }
             Entry::Vacant(entry) => {
-                let publisher = Publisher::new::<T, _>(
+                let publisher = Publisher::new(
                     format!("{}:0", hostname).as_str(),
                     topic,
                     queue_size,
                     caller_id,
+                    latching,
+                    description.clone(),
                 )
                 .await?;
-                entry.insert(publisher).stream::<T>(queue_size)
+                entry
+                    .insert(publisher)
+                    .stream::<T>(queue_size, &description)
             }
         }
     }
@@ -41,6 +61,36 @@ impl PublicationsTracker {
             .map(|publisher| publisher.port)
     }
 
+    /// Returns the topics that are published
+    pub async fn topics(&self) -> Vec<Topic> {
+        self.mapping
+            .lock()
+            .await
+            .values()
+            .map(|publisher| publisher.topic.clone())
+            .collect()
+    }
+
+    /// Returns the connections of all publishers to their subscribers
+    pub async fn connections(&self) -> Vec<ConnectionInfo> {
+        self.mapping
+            .lock()
+            .await
+            .values()
+            .flat_map(Publisher::connections)
+            .collect()
+    }
+
+    /// Returns the connections of every publisher to its subscribers, by topic
+    pub async fn connections_by_topic(&self) -> Vec<(String, Vec<ConnectionInfo>)> {
+        self.mapping
+            .lock()
+            .await
+            .iter()
+            .map(|(topic, publisher)| (topic.clone(), publisher.connections()))
+            .collect()
+    }
+
     /// Removes the specified publications
     pub async fn remove(&self, topic: &str) -> bool {
         self.mapping.lock().await.remove(topic).is_some()
@@ -0,0 +1,66 @@
+use crate::tcpros::{Service, ServiceError, ServicePair};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::future::Future;
+use tokio::sync::Mutex;
+
+#[derive(Default)]
+pub struct ServicesTracker {
+    mapping: Mutex<HashMap<String, (String, Service)>>,
+}
+
+impl ServicesTracker {
+    /// Starts a service server for the specified service and returns the URI on which it can be
+    /// reached.
+    pub async fn add<S, F, R>(
+        &self,
+        hostname: &str,
+        service: &str,
+        caller_id: &str,
+        handler: F,
+    ) -> Result<String, ServiceError>
+    where
+        S: ServicePair,
+        F: Fn(S::Request) -> R + Send + Sync + 'static,
+        R: Future<Output = Result<S::Response, String>> + Send + 'static,
+    {
+        match self.mapping.lock().await.entry(service.to_owned()) {
+            Entry::Occupied(..) => Err(ServiceError::DuplicateService {
+                service: service.to_owned(),
+            }),
+            Entry::Vacant(entry) => {
+                let server = Service::new::<S, _, _, _>(
+                    format!("{}:0", hostname).as_str(),
+                    service,
+                    caller_id,
+                    handler,
+                )
+                .await?;
+                let api = format!("rosrpc://{}:{}", hostname, server.port);
+                entry.insert((api.clone(), server));
+                Ok(api)
+            }
+        }
+    }
+
+    /// Removes the specified service and returns the URI it was reachable at, or None if no such
+    /// service exists.
+    pub async fn remove(&self, service: &str) -> Option<String> {
+        self.mapping
+            .lock()
+            .await
+            .remove(service)
+            .map(|(api, _)| api)
+    }
+
+    /// Removes all the services and returns the names and URIs of all the services that were
+    /// released.
+    pub async fn remove_all(&self) -> Vec<(String, String)> {
+        self.mapping
+            .lock()
+            .await
+            .drain()
+            .map(|(service, (api, _))| (service, api))
+            .collect()
+    }
+}
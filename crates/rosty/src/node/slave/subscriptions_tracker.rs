@@ -1,117 +1,226 @@
 use crate::node::error::SubscriptionError;
-use crate::rosxmlrpc;
-use crate::rosxmlrpc::{Response, ResponseError};
-use crate::tcpros::{IncomingMessage, Message, Subscriber};
-use futures::TryFutureExt;
-use std::collections::hash_map::Entry;
+use crate::tcpros::{ConnectionInfo, IncomingMessage, Subscriber, SubscriberMessage};
+use crate::Topic;
 use std::collections::HashMap;
-use tokio::sync::{mpsc, Mutex};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch, Mutex};
+
+/// A subscription of the tracker. A subscription is pending while it is being registered with
+/// the master, receivers that want to join it wait until the registration finished.
+enum Subscription {
+    Pending {
+        registered: watch::Receiver<()>,
+        /// The publishers of the last `publisherUpdate` received during the registration
+        publishers: Option<Vec<String>>,
+    },
+    Active(Subscriber),
+}
+
+impl Subscription {
+    /// Returns the subscriber if the subscription is registered
+    fn active(&self) -> Option<&Subscriber> {
+        match self {
+            Subscription::Active(subscriber) => Some(subscriber),
+            Subscription::Pending { .. } => None,
+        }
+    }
+}
+
+type Mapping = Arc<Mutex<HashMap<String, Subscription>>>;
+
+/// Removes a pending subscription if its registration is cancelled before it finished. Waiting
+/// receivers are woken when this is dropped.
+struct PendingRegistration {
+    mapping: Mapping,
+    topic: String,
+    finished: bool,
+    _sender: watch::Sender<()>,
+}
+
+impl Drop for PendingRegistration {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let remove = |mapping: &mut HashMap<String, Subscription>, topic: &str| {
+            if let Some(Subscription::Pending { .. }) = mapping.get(topic) {
+                mapping.remove(topic);
+            }
+        };
+        match self.mapping.try_lock() {
+            Ok(mut mapping) => remove(&mut mapping, &self.topic),
+            Err(_) => {
+                let mapping = self.mapping.clone();
+                let topic = std::mem::take(&mut self.topic);
+                tokio::spawn(async move { remove(&mut *mapping.lock().await, &topic) });
+            }
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct SubscriptionsTracker {
-    mapping: Mutex<HashMap<String, Subscriber>>,
+    mapping: Mapping,
 }
 
 impl SubscriptionsTracker {
-    /// Tries to add a subscription to the tracker
-    pub async fn add<T: Message>(
+    /// Adds a receiver for the messages of the specified topic to the tracker. The first receiver
+    /// of a topic creates the subscription and registers it with `register`, which returns the
+    /// current publishers of the topic. Later receivers share the subscription, they wait until
+    /// the registration finished so no receiver joins a subscription that failed to register.
+    /// Other topics can be used while a subscription is registered. Returns the id of the receiver
+    /// and its channel.
+    pub async fn add<T, F, R>(
         &self,
         name: &str,
         topic: &str,
         queue_size: usize,
-    ) -> Result<mpsc::Receiver<IncomingMessage<T>>, SubscriptionError> {
-        match self.mapping.lock().await.entry(String::from(topic)) {
-            Entry::Occupied(..) => Err(SubscriptionError::DuplicateSubscription {
-                topic: topic.to_owned(),
-            }),
-            Entry::Vacant(entry) => {
-                let (subscriber, channel) = Subscriber::new::<T>(name, topic, queue_size);
-                entry.insert(subscriber);
-                Ok(channel)
-            }
-        }
+        register: F,
+    ) -> Result<(usize, mpsc::Receiver<IncomingMessage<T>>), SubscriptionError>
+    where
+        T: SubscriberMessage,
+        F: FnOnce() -> R,
+        R: Future<Output = Result<Vec<String>, SubscriptionError>>,
+    {
+        let mut pending = loop {
+            let mut mapping = self.mapping.lock().await;
+            let mut registered = match mapping.get_mut(topic) {
+                Some(Subscription::Active(subscriber)) => {
+                    return subscriber.add_receiver::<T>(queue_size).ok_or_else(|| {
+                        SubscriptionError::TypeMismatch {
+                            topic: topic.to_owned(),
+                            data_type: subscriber.topic().data_type.clone(),
+                        }
+                    })
+                }
+                Some(Subscription::Pending { registered, .. }) => registered.clone(),
+                None => {
+                    let (sender, registered) = watch::channel(());
+                    mapping.insert(
+                        topic.to_owned(),
+                        Subscription::Pending {
+                            registered,
+                            publishers: None,
+                        },
+                    );
+                    break PendingRegistration {
+                        mapping: self.mapping.clone(),
+                        topic: topic.to_owned(),
+                        finished: false,
+                        _sender: sender,
+                    };
+                }
+            };
+            // The registration is finished when the sender is dropped
+            drop(mapping);
+            while registered.recv().await.is_some() {}
+        };
+
+        let mut subscriber = Subscriber::new::<T>(name, topic);
+        let (id, channel) = subscriber
+            .add_receiver::<T>(queue_size)
+            .expect("a new subscription accepts its own type");
+        let publishers = register().await;
+
+        let mut mapping = self.mapping.lock().await;
+        pending.finished = true;
+        let updated = match mapping.remove(topic) {
+            Some(Subscription::Pending { publishers, .. }) => publishers,
+            _ => None,
+        };
+        let publishers = publishers?;
+        subscriber.set_publishers(updated.unwrap_or(publishers));
+        mapping.insert(topic.to_owned(), Subscription::Active(subscriber));
+        drop(mapping);
+        drop(pending);
+        Ok((id, channel))
     }
 
-    /// Notifies this instance that the given publishers
-    pub async fn add_publishers<T>(
-        &self,
-        topic: &str,
-        name: &str,
-        publishers: T,
-    ) -> Result<(), SubscriptionError>
+    /// Notifies this instance that the given publishers are the current publishers of the
+    /// topic. Links to publishers that are no longer listed are closed.
+    pub async fn set_publishers<T>(&self, topic: &str, publishers: T)
     where
         T: Iterator<Item = String>,
     {
-        let mut last_error_message = None;
-        if let Some(subscription) = self.mapping.lock().await.get_mut(topic) {
-            // Get information on the topic from all publishers concurrently
-            let publishers = futures::future::join_all(
-                publishers
-                    .filter(|publisher| !subscription.is_connected_to(publisher))
-                    .map(|publisher| get_publisher_topic_info(topic, name, publisher)),
-            )
-            .await;
-
-            // For every publisher, try to connect to it or if there was an error, report the error.
-            for publisher in publishers {
-                let result = match publisher {
-                    Ok((publisher, hostname, port)) => subscription
-                        .connect_to(&publisher, (hostname.as_str(), port as u16))
-                        .await
-                        .map_err(SubscriptionError::PublisherConnectError),
-                    Err(e) => Err(e),
-                };
-
-                if let Err(e) = result {
-                    last_error_message = Some(e)
-                }
-            }
+        match self.mapping.lock().await.get_mut(topic) {
+            Some(Subscription::Active(subscriber)) => subscriber.set_publishers(publishers),
+            Some(Subscription::Pending {
+                publishers: pending,
+                ..
+            }) => *pending = Some(publishers.collect()),
+            None => {}
         }
+    }
 
-        // Returns the last error
-        match last_error_message {
-            None => Ok(()),
-            Some(err) => Err(err),
-        }
+    /// Returns the topics that are subscribed to
+    pub async fn topics(&self) -> Vec<Topic> {
+        self.mapping
+            .lock()
+            .await
+            .values()
+            .filter_map(Subscription::active)
+            .map(|subscriber| subscriber.topic().clone())
+            .collect()
     }
 
-    /// Removes the specified subscription
-    pub async fn remove(&self, topic: &str) -> bool {
-        self.mapping.lock().await.remove(topic).is_some()
+    /// Returns the connections of all subscriptions to their publishers
+    pub async fn connections(&self) -> Vec<ConnectionInfo> {
+        self.mapping
+            .lock()
+            .await
+            .values()
+            .filter_map(Subscription::active)
+            .flat_map(Subscriber::connections)
+            .collect()
     }
 
-    /// Removes all the subscriptions and returns an iterator of all the topics that were released.
-    pub async fn remove_all(&self) -> Vec<String> {
-        self.mapping.lock().await.drain().map(|(k, _)| k).collect()
+    /// Returns the connections of every subscription to its publishers, by topic
+    pub async fn connections_by_topic(&self) -> Vec<(String, Vec<ConnectionInfo>)> {
+        self.mapping
+            .lock()
+            .await
+            .iter()
+            .filter_map(|(topic, subscription)| {
+                let subscriber = subscription.active()?;
+                Some((topic.clone(), subscriber.connections()))
+            })
+            .collect()
     }
-}
 
-async fn get_publisher_topic_info(
-    topic: &str,
-    name: &str,
-    publisher: String,
-) -> Result<(String, String, i32), SubscriptionError> {
-    let publisher_result = publisher.clone();
-    request_topic(publisher, name, topic)
-        .map_err(SubscriptionError::RequestTopicError)
-        .and_then(|(protocol, hostname, port)| async move {
-            if protocol != "TCPROS" {
-                return Err(SubscriptionError::ProtocolMismatch(protocol));
-            }
-            Ok((publisher_result, hostname, port))
-        })
-        .await
-}
+    /// Returns the connections of the subscription to the specified topic
+    pub async fn topic_connections(&self, topic: &str) -> Vec<ConnectionInfo> {
+        self.mapping
+            .lock()
+            .await
+            .get(topic)
+            .and_then(Subscription::active)
+            .map(Subscriber::connections)
+            .unwrap_or_default()
+    }
 
-async fn request_topic(
-    publisher_uri: String,
-    caller_id: &str,
-    topic: &str,
-) -> Response<(String, String, i32)> {
-    let uri = publisher_uri
-        .parse()
-        .map_err(|_| ResponseError::Client(format!("invalid uri '{}'", publisher_uri)))?;
-    rosxmlrpc::Client::new(uri)
-        .request("requestTopic", &(caller_id, topic, [["TCPROS"]]))
-        .await
+    /// Removes the receiver with the given id from the subscription of the specified topic. Returns
+    /// true if this was the last receiver, in which case the subscription itself is removed.
+    pub async fn remove(&self, topic: &str, id: usize) -> bool {
+        let mut mapping = self.mapping.lock().await;
+        let is_empty = match mapping.get_mut(topic) {
+            Some(Subscription::Active(subscriber)) => subscriber.remove_receiver(id),
+            _ => false,
+        };
+        if is_empty {
+            mapping.remove(topic);
+        }
+        is_empty
+    }
+
+    /// Removes all the subscriptions and returns the topics of the registered subscriptions that
+    /// were released.
+    pub async fn remove_all(&self) -> Vec<String> {
+        self.mapping
+            .lock()
+            .await
+            .drain()
+            .filter_map(|(topic, subscription)| subscription.active().map(|_| topic))
+            .collect()
+    }
 }
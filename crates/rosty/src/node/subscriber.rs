@@ -1,36 +1,59 @@
 use super::slave::Slave;
 use crate::node::error::SubscriptionError;
-use crate::tcpros::{IncomingMessage, Message};
+use crate::tcpros::{ConnectionInfo, IncomingMessage, SubscriberMessage};
 use futures::Stream;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::sync::mpsc;
 
-pub struct Subscriber<T: Message> {
+/// A subscription to a topic. Multiple subscriptions to the same topic share their connections
+/// to publishers but each has its own queue of incoming messages. A `Subscriber<RawMessage>`
+/// receives the serialized messages of any type.
+pub struct Subscriber<T: SubscriberMessage> {
     slave: Arc<Slave>,
     name: String,
+    id: usize,
+    registered: bool,
     channel: mpsc::Receiver<IncomingMessage<T>>,
 }
 
-impl<T: Message> Subscriber<T> {
+impl<T: SubscriberMessage> Subscriber<T> {
     pub(crate) async fn new(
         slave: Arc<Slave>,
         name: &str,
         queue_size: usize,
     ) -> Result<Self, SubscriptionError> {
         // Register the subscription with the slave
-        let channel = slave.add_subscription::<T>(name, queue_size).await?;
+        let (id, channel) = slave.add_subscription::<T>(name, queue_size).await?;
 
         Ok(Self {
             slave,
             name: name.to_owned(),
+            id,
+            registered: true,
             channel,
         })
     }
 }
 
-impl<T: Message> Stream for Subscriber<T> {
+impl<T: SubscriberMessage> Subscriber<T> {
+    /// Returns a snapshot of the statistics of the links to all publishers of the topic. The links
+    /// are shared by all subscriptions to the topic.
+    pub async fn stats(&self) -> Vec<ConnectionInfo> {
+        self.slave.subscription_connections(&self.name).await
+    }
+
+    /// Removes the subscription and waits until it is removed. When this is the last subscription
+    /// to the topic the master is notified before this function returns, unlike when the
+    /// subscriber is dropped.
+    pub async fn unsubscribe(mut self) {
+        self.registered = false;
+        self.slave.remove_subscription(&self.name, self.id).await;
+    }
+}
+
+impl<T: SubscriberMessage> Stream for Subscriber<T> {
     type Item = IncomingMessage<T>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
@@ -38,13 +61,17 @@ impl<T: Message> Stream for Subscriber<T> {
     }
 }
 
-impl<T: Message> Drop for Subscriber<T> {
+impl<T: SubscriberMessage> Drop for Subscriber<T> {
     fn drop(&mut self) {
+        if !self.registered {
+            return;
+        }
         let name = self.name.clone();
+        let id = self.id;
         let slave = self.slave.clone();
         if let Ok(runtime) = tokio::runtime::Handle::try_current() {
             runtime.spawn(async move {
-                slave.remove_subscription(&name).await;
+                slave.remove_subscription(&name, id).await;
             });
         }
     }
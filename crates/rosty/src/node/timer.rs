@@ -0,0 +1,105 @@
+use crate::node::clock::Clock;
+use futures::Stream;
+use rosty_msg::{Duration, Time};
+use std::sync::Arc;
+
+/// Timing information of a single expiration of a `Timer`, like roscpp's `TimerEvent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerEvent {
+    /// The time the previous expiration was expected, `None` for the first expiration and after
+    /// the time jumped backwards
+    pub last_expected: Option<Time>,
+
+    /// The time the previous expiration actually happened, `None` for the first expiration and
+    /// after the time jumped backwards
+    pub last_real: Option<Time>,
+
+    /// The time this expiration was expected
+    pub current_expected: Time,
+
+    /// The time this expiration actually happened
+    pub current_real: Time,
+}
+
+/// A timer that expires periodically or once according to the clock of the node. With simulated
+/// time the timer follows the `/clock` topic, so it pauses when the simulation is paused and
+/// restarts when the time jumps backwards.
+pub struct Timer {
+    clock: Arc<Clock>,
+    period: Duration,
+    oneshot: bool,
+    next: Option<Time>,
+    last: Option<(Time, Time)>,
+    backwards_jumps: u64,
+}
+
+impl Timer {
+    pub(crate) fn new(clock: Arc<Clock>, period: Duration, oneshot: bool) -> Timer {
+        Timer {
+            backwards_jumps: clock.backwards_jumps(),
+            clock,
+            period,
+            oneshot,
+            next: None,
+            last: None,
+        }
+    }
+
+    /// Returns the time between two expirations, or before the expiration of a one-shot timer
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Waits for the next expiration of the timer. The first expiration is one period after the
+    /// first call. Returns `None` after a one-shot timer has expired.
+    pub async fn tick(&mut self) -> Option<TimerEvent> {
+        if self.oneshot && self.last.is_some() {
+            return None;
+        }
+
+        let expected = loop {
+            // Restart the timer when the time jumped backwards
+            let backwards_jumps = self.clock.backwards_jumps();
+            if backwards_jumps != self.backwards_jumps {
+                self.backwards_jumps = backwards_jumps;
+                self.next = None;
+                self.last = None;
+            }
+            let expected = match self.next {
+                Some(expected) => expected,
+                None => self.clock.wait_for_now().await + self.period,
+            };
+            self.next = Some(expected);
+            if self.clock.sleep_until(expected).await {
+                break expected;
+            }
+        };
+        let real = self.clock.now().unwrap_or(expected);
+
+        let event = TimerEvent {
+            last_expected: self.last.map(|(expected, _)| expected),
+            last_real: self.last.map(|(_, real)| real),
+            current_expected: expected,
+            current_real: real,
+        };
+        self.last = Some((expected, real));
+
+        // Skip the expirations that were missed instead of firing them all at once
+        let next = expected + self.period;
+        self.next = Some(if next <= real {
+            real + self.period
+        } else {
+            next
+        });
+
+        Some(event)
+    }
+
+    /// Converts the timer into a stream of its expirations
+    pub fn into_stream(self) -> impl Stream<Item = TimerEvent> {
+        futures::stream::unfold(self, |mut timer| async move {
+            let event = timer.tick().await?;
+            Some((event, timer))
+        })
+    }
+}
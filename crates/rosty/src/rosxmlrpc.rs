@@ -13,7 +13,7 @@ mod client;
 mod response_info;
 mod server;
 
-pub use client::Client;
+pub use client::{from_value, Client};
 pub use server::ServerBuilder;
 pub use xmlrpc::{Params, Value};
 
@@ -44,10 +44,15 @@ impl Client {
         D: Deserialize<'a>,
     {
         let data = self.request_tree(name, params).await?;
-        Deserialize::deserialize(data).map_err(bad_response_structure)
+        from_value(data)
     }
 }
 
+/// Converts the value returned by a call to the expected type
+pub fn from_value<'a, D: Deserialize<'a>>(value: Value) -> Response<D> {
+    Deserialize::deserialize(value).map_err(bad_response_structure)
+}
+
 fn bad_request_structure<T: ::std::fmt::Display>(err: T) -> ResponseError {
     ResponseError::Client(format!("Failed to serialize parameters: {}", err))
 }
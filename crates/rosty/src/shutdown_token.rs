@@ -1,31 +1,69 @@
 use futures::task::AtomicWaker;
+use once_cell::sync::OnceCell;
+use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-/// A token that can be used to signal that we want to shutdown. The token can be cloned so it can
-/// be passed around and it can be waited upon.
-#[derive(Debug, Clone)]
-pub struct ShutdownToken(Arc<(AtomicBool, AtomicWaker)>);
+/// The reason the node was shut down
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ShutdownReason {
+    /// The shutdown was requested by calling `shutdown`
+    Requested,
+
+    /// The process received an interrupt signal (Ctrl+C)
+    Interrupted,
+
+    /// The master or another node requested the shutdown through the slave API, with the given
+    /// message. The master does this when another node registers with the same name.
+    Remote(String),
+}
 
-impl Default for ShutdownToken {
-    fn default() -> Self {
-        ShutdownToken(Arc::new((AtomicBool::new(false), Default::default())))
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownReason::Requested => write!(f, "shutdown requested"),
+            ShutdownReason::Interrupted => write!(f, "interrupted"),
+            ShutdownReason::Remote(message) => write!(f, "{}", message),
+        }
     }
 }
 
+#[derive(Debug, Default)]
+struct Inner {
+    is_shutdown: AtomicBool,
+    waker: AtomicWaker,
+    reason: OnceCell<ShutdownReason>,
+}
+
+/// A token that can be used to signal that we want to shutdown. The token can be cloned so it can
+/// be passed around and it can be waited upon.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownToken(Arc<Inner>);
+
 impl ShutdownToken {
     /// Returns true if the token is indicating a shutdown
     pub fn is_awaiting_shutdown(&self) -> bool {
-        (self.0).0.load(Ordering::Relaxed)
+        self.0.is_shutdown.load(Ordering::Relaxed)
     }
 
     /// Tell the token to go to shutdown state
     pub fn shutdown(&self) {
-        (self.0).0.store(true, Ordering::Relaxed);
-        (self.0).1.wake();
+        self.0.is_shutdown.store(true, Ordering::Relaxed);
+        self.0.waker.wake();
+    }
+
+    /// Tell the token to go to shutdown state and record why. Only the first reason is kept.
+    pub fn shutdown_with_reason(&self, reason: ShutdownReason) {
+        let _ = self.0.reason.set(reason);
+        self.shutdown();
+    }
+
+    /// Returns the reason of the shutdown if one was given
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        self.0.reason.get().cloned()
     }
 }
 
@@ -36,7 +74,7 @@ impl Future for ShutdownToken {
         if self.is_awaiting_shutdown() {
             Poll::Ready(())
         } else {
-            (self.0).1.register(cx.waker());
+            self.0.waker.register(cx.waker());
 
             if self.is_awaiting_shutdown() {
                 Poll::Ready(())
@@ -1,16 +1,149 @@
 mod header;
 mod publisher;
+mod service;
 mod subscriber;
 
 use byteorder::{LittleEndian, WriteBytesExt};
-pub use publisher::{Publisher, PublisherError, PublisherSendError, PublisherStream};
-pub use rosty_msg::Message;
+pub use publisher::{
+    Publisher, PublisherError, PublisherSendError, PublisherStream, SubscriberEvent,
+};
+pub use rosty_msg::{Message, ServicePair};
+pub use service::{probe_service, Service, ServiceCallError, ServiceConnection, ServiceError};
 use std::io;
 use std::io::Cursor;
-pub use subscriber::{IncomingMessage, PublisherConnectError, Subscriber};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+pub use subscriber::{IncomingMessage, RawMessage, Subscriber, SubscriberMessage};
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
 
+/// Describes the type of the messages that are exchanged over a connection
+#[derive(Clone, Debug)]
+pub struct MessageDescription {
+    msg_type: String,
+    md5sum: String,
+    msg_definition: String,
+}
+
+impl MessageDescription {
+    pub fn new(msg_type: &str, md5sum: &str, msg_definition: &str) -> Self {
+        MessageDescription {
+            msg_type: msg_type.to_owned(),
+            md5sum: md5sum.to_owned(),
+            msg_definition: msg_definition.to_owned(),
+        }
+    }
+
+    /// Returns the description of the message type `T`
+    pub fn of<T: Message>() -> Self {
+        MessageDescription {
+            msg_type: T::msg_type(),
+            md5sum: T::md5sum(),
+            msg_definition: T::msg_definition(),
+        }
+    }
+
+    /// Returns the type of the messages, `*` for any type
+    pub fn msg_type(&self) -> &str {
+        &self.msg_type
+    }
+
+    /// Returns the md5sum of the message type, `*` for any type
+    pub fn md5sum(&self) -> &str {
+        &self.md5sum
+    }
+
+    /// Returns the full definition of the message type
+    pub fn msg_definition(&self) -> &str {
+        &self.msg_definition
+    }
+}
+
+/// The direction in which data flows over a connection
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Direction {
+    /// Messages are received from a publisher
+    Inbound,
+
+    /// Messages are sent to a subscriber
+    Outbound,
+}
+
+/// Describes a single connection between a publisher and a subscriber
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConnectionInfo {
+    /// An id that is unique within the process
+    pub id: usize,
+
+    /// The uri of the publisher for inbound connections, the caller id of the subscriber for
+    /// outbound connections
+    pub destination: String,
+
+    pub direction: Direction,
+    pub topic: String,
+
+    /// Whether the connection is currently established
+    pub connected: bool,
+
+    pub stats: ConnectionStats,
+}
+
+/// A snapshot of the data that flowed over a connection
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct ConnectionStats {
+    /// The number of bytes sent or received, including the length of every message
+    pub bytes: u64,
+
+    /// The number of messages sent or received
+    pub messages: u64,
+
+    /// The number of messages that were dropped because a queue was full
+    pub drops: u64,
+}
+
+/// Counts the data that flows over a connection
+#[derive(Debug, Default)]
+struct Counters {
+    bytes: AtomicU64,
+    messages: AtomicU64,
+    drops: AtomicU64,
+}
+
+impl Counters {
+    /// Records a message of `bytes` bytes that was sent or received
+    fn message(&self, bytes: usize) {
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a number of dropped messages
+    fn dropped(&self, count: u64) {
+        self.drops.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> ConnectionStats {
+        ConnectionStats {
+            bytes: self.bytes.load(Ordering::Relaxed),
+            messages: self.messages.load(Ordering::Relaxed),
+            drops: self.drops.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static NEXT_CONNECTION_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a new id for a connection
+fn next_connection_id() -> usize {
+    NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Encodes the length of `data` in front of it to form a packet
+fn encode_packet(data: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(data.len() + std::mem::size_of::<u32>());
+    packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    packet.extend_from_slice(data);
+    packet
+}
+
 /// Read a packet from a stream. A packet firstly consists of a u32 little endian encoded length
 /// followed by the rest of the packet. The returned vector also includes this initial length.
 async fn read_packet<U: AsyncRead + Unpin>(stream: &mut U) -> Result<Vec<u8>, io::Error> {
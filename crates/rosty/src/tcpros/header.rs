@@ -30,8 +30,18 @@ pub async fn encode_and_write<W: tokio::io::AsyncWrite + Unpin>(
     let mut writer = io::Cursor::new(Vec::with_capacity(128));
     header.encode(&mut writer)?;
     let data = writer.into_inner();
-    stream.write(&data).await?;
-    Ok(())
+    stream.write_all(&data).await
+}
+
+/// Writes a header that only contains an `error` field to the given `stream`. This is how the
+/// accepting side of a connection explains why it rejects a handshake.
+pub async fn write_error<W: tokio::io::AsyncWrite + Unpin>(
+    stream: &mut W,
+    error: &str,
+) -> Result<(), io::Error> {
+    let mut fields = HashMap::<String, String>::new();
+    fields.insert(String::from("error"), error.to_owned());
+    encode_and_write(stream, &fields).await
 }
 
 /// Given a `header` ensure that one of its `field`s it set to a specific `expected` value.
@@ -1,14 +1,20 @@
 use super::header;
+use crate::node::NameError;
 use crate::rosxmlrpc::ResponseError;
 use crate::shutdown_token::ShutdownToken;
-use crate::tcpros::Message;
+use crate::tcpros::{
+    next_connection_id, ConnectionInfo, Counters, Direction, Message, MessageDescription,
+    RawMessage,
+};
 use crate::Topic;
 use failure::_core::marker::PhantomData;
 use futures::StreamExt;
-use std::collections::HashMap;
-use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::{TcpListener, ToSocketAddrs};
 use tokio::sync::broadcast::{self, RecvError};
+use tokio::sync::watch;
 use tracing_futures::Instrument;
 
 #[derive(Debug, Fail)]
@@ -24,6 +30,15 @@ pub enum PublisherError {
 
     #[fail(display = "registration error")]
     RegistrationError(#[fail(cause)] ResponseError),
+
+    #[fail(
+        display = "already publishing topic '{}' with type '{}'",
+        topic, data_type
+    )]
+    TypeMismatch { topic: String, data_type: String },
+
+    #[fail(display = "{}", 0)]
+    InvalidName(NameError),
 }
 
 #[derive(Debug, Fail)]
@@ -57,8 +72,154 @@ pub struct Publisher {
     pub topic: Topic,
     pub port: u16,
     shutdown_token: ShutdownToken,
+    md5sum: String,
 
     sender: broadcast::Sender<Vec<u8>>,
+    latch: Latch,
+    connections: SubscriberConnections,
+}
+
+/// Describes a change in the set of subscribers connected to a publisher. Both variants carry the
+/// caller id of the subscriber.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubscriberEvent {
+    Connected(String),
+    Disconnected(String),
+}
+
+/// Keeps track of the number of subscribers connected to a publisher and notifies listeners about
+/// subscribers that connect or disconnect.
+#[derive(Clone)]
+pub struct SubscriberConnections(Arc<SubscriberConnectionsInner>);
+
+struct SubscriberConnectionsInner {
+    topic: String,
+
+    /// The caller id and counters of the subscriber of every connection, by connection id
+    subscribers: Mutex<BTreeMap<usize, (String, Arc<Counters>)>>,
+    count_tx: watch::Sender<usize>,
+    count_rx: watch::Receiver<usize>,
+    events: broadcast::Sender<SubscriberEvent>,
+}
+
+impl SubscriberConnections {
+    fn new(topic: &str) -> Self {
+        let (count_tx, count_rx) = watch::channel(0);
+        let (events, _) = broadcast::channel(32);
+        SubscriberConnections(Arc::new(SubscriberConnectionsInner {
+            topic: topic.to_owned(),
+            subscribers: Mutex::new(BTreeMap::new()),
+            count_tx,
+            count_rx,
+            events,
+        }))
+    }
+
+    /// Returns the number of currently connected subscribers
+    pub fn count(&self) -> usize {
+        self.0.subscribers.lock().unwrap().len()
+    }
+
+    /// Returns a receiver that is notified every time the number of subscribers changes
+    pub fn watch_count(&self) -> watch::Receiver<usize> {
+        self.0.count_rx.clone()
+    }
+
+    /// Returns a receiver of all subscriber connect and disconnect events from now on
+    pub fn events(&self) -> broadcast::Receiver<SubscriberEvent> {
+        self.0.events.subscribe()
+    }
+
+    /// Returns a snapshot of the connections to all connected subscribers
+    pub fn snapshot(&self) -> Vec<ConnectionInfo> {
+        self.0
+            .subscribers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, (caller_id, counters))| ConnectionInfo {
+                id: *id,
+                destination: caller_id.clone(),
+                direction: Direction::Outbound,
+                topic: self.0.topic.clone(),
+                connected: true,
+                stats: counters.snapshot(),
+            })
+            .collect()
+    }
+
+    /// Adds the connection of a subscriber. Returns the id of the connection and the counters of
+    /// the data sent over it.
+    fn connected(&self, caller_id: &str) -> (usize, Arc<Counters>) {
+        let id = next_connection_id();
+        let counters = Arc::new(Counters::default());
+        self.update(|subscribers| {
+            subscribers.insert(id, (caller_id.to_owned(), counters.clone()));
+        });
+        let _ = self
+            .0
+            .events
+            .send(SubscriberEvent::Connected(caller_id.to_owned()));
+        (id, counters)
+    }
+
+    fn disconnected(&self, id: usize) {
+        let mut subscriber = None;
+        self.update(|subscribers| subscriber = subscribers.remove(&id));
+        if let Some((caller_id, _)) = subscriber {
+            let _ = self.0.events.send(SubscriberEvent::Disconnected(caller_id));
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<usize, (String, Arc<Counters>)>)) {
+        let mut subscribers = self.0.subscribers.lock().unwrap();
+        f(&mut subscribers);
+        let _ = self.0.count_tx.broadcast(subscribers.len());
+    }
+}
+
+/// Keeps track of the last message sent by a latching publisher so it can be sent to subscribers
+/// that connect later on.
+#[derive(Clone)]
+struct Latch {
+    latching: bool,
+    last_message: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl Latch {
+    fn new(latching: bool) -> Self {
+        Latch {
+            latching,
+            last_message: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Broadcasts the `message` to all connected subscribers and stores it if latching is enabled
+    fn send(&self, sender: &broadcast::Sender<Vec<u8>>, message: Vec<u8>) {
+        let mut last_message = self.last_message.lock().unwrap();
+        if self.latching {
+            *last_message = Some(message.clone());
+        }
+        let _ = sender.send(message);
+    }
+
+    /// Subscribes to the messages broadcast by `sender`. The returned latched message and the
+    /// receiver are obtained atomically so no message is lost or duplicated.
+    fn subscribe(
+        &self,
+        sender: &broadcast::Sender<Vec<u8>>,
+    ) -> (Option<Vec<u8>>, broadcast::Receiver<Vec<u8>>) {
+        let last_message = self.last_message.lock().unwrap();
+        (last_message.clone(), sender.subscribe())
+    }
+}
+
+/// The information a publisher sends to subscribers during the handshake
+struct HandshakeInfo {
+    topic: String,
+    caller_id: String,
+    latching: bool,
+    description: MessageDescription,
 }
 
 impl Drop for Publisher {
@@ -68,14 +229,15 @@ impl Drop for Publisher {
 }
 
 impl Publisher {
-    pub async fn new<T, U>(
+    pub async fn new<U>(
         address: U,
         topic: &str,
         queue_size: usize,
         caller_id: &str,
+        latching: bool,
+        description: MessageDescription,
     ) -> Result<Publisher, PublisherError>
     where
-        T: Message,
         U: ToSocketAddrs,
     {
         let shutdown_token = ShutdownToken::default();
@@ -88,22 +250,32 @@ impl Publisher {
         let port = socket_addr.port();
 
         let (sender, _) = broadcast::channel(queue_size);
+        let latch = Latch::new(latching);
+        let connections = SubscriberConnections::new(topic);
 
         // Construct a future that will accept incoming connections
-        let topic_str = topic.to_owned();
-        let caller_id_str = caller_id.to_owned();
+        let data_type = description.msg_type().to_owned();
+        let md5sum = description.md5sum().to_owned();
+        let info = Arc::new(HandshakeInfo {
+            topic: topic.to_owned(),
+            caller_id: caller_id.to_owned(),
+            latching,
+            description,
+        });
 
         // Accept connections until the publisher is shut down
         let accept_shutdown_token = shutdown_token.clone();
         let sender_for_receivers = sender.clone();
+        let latch_for_receivers = latch.clone();
+        let connections_for_receivers = connections.clone();
         tokio::spawn(async move {
             let accept_future = listener
                 .incoming()
                 .for_each_concurrent(None, move |stream| {
-                    let topic_str = topic_str.clone();
-                    let topic_str2 = topic_str.clone();
-                    let caller_id_str = caller_id_str.to_owned();
-                    let receiver = sender_for_receivers.subscribe();
+                    let info = info.clone();
+                    let (latched_message, receiver) =
+                        latch_for_receivers.subscribe(&sender_for_receivers);
+                    let connections = connections_for_receivers.clone();
                     async move {
                         match stream {
                             Ok(stream) => {
@@ -111,25 +283,23 @@ impl Publisher {
                                     .peer_addr()
                                     .expect("must have a peer addr")
                                     .to_string();
+                                let span = tracing::info_span!(
+                                    "publisher",
+                                    topic = info.topic.as_str(),
+                                    remote = remote.as_str()
+                                );
                                 tokio::spawn(
                                     async move {
-                                        let topic_str = topic_str.clone();
-                                        let caller_id_str = caller_id_str.to_owned();
-                                        process_subscriber::<T, _>(
-                                            &topic_str,
+                                        process_subscriber(
+                                            &info,
                                             stream,
-                                            &caller_id_str,
+                                            latched_message,
                                             receiver,
+                                            connections,
                                         )
                                         .await;
                                     }
-                                    .instrument(
-                                        tracing::info_span!(
-                                            "publisher",
-                                            topic = topic_str2.as_str(),
-                                            remote = remote.as_str()
-                                        ),
-                                    ),
+                                    .instrument(span),
                                 );
                             }
                             Err(e) => error!("incoming connection failed: {}", e),
@@ -144,113 +314,206 @@ impl Publisher {
         Ok(Publisher {
             topic: Topic {
                 name: topic.to_owned(),
-                data_type: T::msg_type(),
+                data_type,
             },
+            md5sum,
             sender,
+            latch,
+            connections,
             port,
             shutdown_token,
         })
     }
 
-    pub fn stream<T: Message>(
+    /// Returns a new stream to send messages to the subscribers of this publisher. Fails if the
+    /// messages are of a different type than those of the publisher.
+    pub fn stream<T>(
         &self,
         _queue_size: usize,
+        description: &MessageDescription,
     ) -> Result<PublisherStream<T>, PublisherError> {
+        if description.md5sum() != self.md5sum {
+            return Err(PublisherError::TypeMismatch {
+                topic: self.topic.name.clone(),
+                data_type: self.topic.data_type.clone(),
+            });
+        }
         let stream = PublisherStream {
             datatype: PhantomData::default(),
             sender: self.sender.clone(),
+            latch: self.latch.clone(),
+            connections: self.connections.clone(),
         };
         Ok(stream)
     }
+
+    /// Returns a snapshot of the connections to the subscribers of this publisher
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        self.connections.snapshot()
+    }
+
+    /// Returns true if this publisher sends the last message to newly connected subscribers
+    pub fn is_latching(&self) -> bool {
+        self.latch.latching
+    }
 }
 
-async fn process_subscriber<T, U>(
-    topic: &str,
+async fn process_subscriber<U>(
+    info: &HandshakeInfo,
     mut stream: U,
-    pub_caller_id: &str,
+    latched_message: Option<Vec<u8>>,
     mut receiver: broadcast::Receiver<Vec<u8>>,
+    connections: SubscriberConnections,
 ) where
-    T: Message,
     U: AsyncWrite + AsyncRead + Send + Unpin,
 {
     info!("incoming connection");
 
-    let caller_id = match handshake::<T, _>(&mut stream, pub_caller_id, topic).await {
+    let caller_id = match handshake(&mut stream, info).await {
         Ok(caller_id) => caller_id,
         Err(e) => {
             error!("handshake error: {}, aborting..", e);
+            if let PublisherSubcribeError::InvalidHeader(_) = e {
+                // Tell the subscriber why the connection is closed, like roscpp and rospy do
+                if let Err(e) = header::write_error(&mut stream, &e.to_string()).await {
+                    debug!("error sending handshake error: {}", e);
+                }
+            }
             return;
         }
     };
 
+    let (id, counters) = connections.connected(&caller_id);
+
     async {
         info!("connected");
 
-        while let Some(data) = receiver.next().await {
+        // Subscribers do not send any data after the handshake, reading from the stream only
+        // serves to detect when the subscriber closes the connection.
+        let (mut reader, mut writer) = tokio::io::split(stream);
+        let closed = async move {
+            let mut buffer = [0u8; 64];
+            while let Ok(count) = reader.read(&mut buffer).await {
+                if count == 0 {
+                    break;
+                }
+            }
+        };
+        futures::pin_mut!(closed);
+
+        // Send the last message of a latching publisher before any new messages
+        if let Some(message) = latched_message {
+            if let Err(e) = writer.write_all(&message).await {
+                error!("error sending latched message: {}, disconnecting..", e);
+                return;
+            }
+            counters.message(message.len());
+        }
+
+        loop {
+            let data = tokio::select!(
+                data = receiver.next() => data,
+                _ = &mut closed => {
+                    info!("subscriber closed the connection");
+                    break;
+                }
+            );
             match data {
-                Ok(message) => match stream.write_all(&message).await {
-                    Ok(_) => {}
-                    Err(e) => {
+                Some(Ok(message)) => {
+                    if let Err(e) = writer.write_all(&message).await {
                         error!("error sending message: {}, disconnecting..", e);
                         return;
                     }
-                },
-                Err(RecvError::Closed) => {
-                    info!("publisher closed");
-                    break;
+                    counters.message(message.len());
                 }
-                Err(RecvError::Lagged(i)) => {
+                Some(Err(RecvError::Lagged(i))) => {
                     warn!("skipped {} message", i);
+                    counters.dropped(i);
+                }
+                None | Some(Err(RecvError::Closed)) => {
+                    info!("publisher closed");
+                    break;
                 }
             }
         }
     }
     .instrument(tracing::info_span!("caller", id = caller_id.as_str()))
-    .await
+    .await;
+
+    connections.disconnected(id);
 }
 
-async fn handshake<T: Message, U: AsyncRead + AsyncWrite + Unpin>(
+async fn handshake<U: AsyncRead + AsyncWrite + Unpin>(
     stream: &mut U,
-    pub_caller_id: &str,
-    topic: &str,
+    info: &HandshakeInfo,
 ) -> Result<String, PublisherSubcribeError> {
-    let caller_id = read_handshake_request::<T, U>(stream, topic).await?;
-    write_handshake_response::<T, U>(stream, pub_caller_id).await?;
+    let caller_id = read_handshake_request(stream, info).await?;
+    write_handshake_response(stream, info).await?;
     Ok(caller_id)
 }
 
-async fn read_handshake_request<T: Message, U: AsyncRead + Unpin>(
+async fn read_handshake_request<U: AsyncRead + Unpin>(
     mut stream: &mut U,
-    topic: &str,
+    info: &HandshakeInfo,
 ) -> Result<String, PublisherSubcribeError> {
     let fields = header::read_and_decode(&mut stream).await?;
-    header::match_field(&fields, "md5sum", &T::md5sum())?;
-    header::match_field(&fields, "type", &T::msg_type())?;
-    header::match_field(&fields, "topic", topic)?;
+    // Subscribers that accept any type of message send a wildcard instead
+    if fields.get("md5sum").map(String::as_str) != Some("*") {
+        header::match_field(&fields, "md5sum", info.description.md5sum())?;
+    }
+    if fields.get("type").map(String::as_str) != Some("*") {
+        header::match_field(&fields, "type", info.description.msg_type())?;
+    }
+    header::match_field(&fields, "topic", &info.topic)?;
     Ok(fields
         .get("callerid")
         .ok_or_else(|| header::InvalidHeaderError::MissingField("callerid".into()))?
         .clone())
 }
 
-async fn write_handshake_response<T: Message, U: AsyncWrite + Unpin>(
+async fn write_handshake_response<U: AsyncWrite + Unpin>(
     mut stream: &mut U,
-    caller_id: &str,
+    info: &HandshakeInfo,
 ) -> Result<(), PublisherSubcribeError> {
+    let description = &info.description;
     let mut fields = HashMap::<String, String>::new();
-    fields.insert(String::from("md5sum"), T::md5sum());
-    fields.insert(String::from("type"), T::msg_type());
-    fields.insert(String::from("callerid"), caller_id.into());
-    fields.insert(String::from("message_definition"), T::msg_definition());
+    fields.insert(String::from("md5sum"), description.md5sum().to_owned());
+    fields.insert(String::from("type"), description.msg_type().to_owned());
+    fields.insert(String::from("callerid"), info.caller_id.clone());
+    fields.insert(
+        String::from("message_definition"),
+        description.msg_definition().to_owned(),
+    );
+    fields.insert(
+        String::from("latching"),
+        String::from(if info.latching { "1" } else { "0" }),
+    );
     header::encode_and_write(&mut stream, &fields)
         .await
         .map_err(Into::into)
 }
 
 #[derive(Clone)]
-pub struct PublisherStream<T: Message> {
+pub struct PublisherStream<T> {
     datatype: PhantomData<T>,
     sender: broadcast::Sender<Vec<u8>>,
+    latch: Latch,
+    connections: SubscriberConnections,
+}
+
+impl<T> PublisherStream<T> {
+    /// Returns the subscribers that are connected to the publisher
+    pub fn connections(&self) -> &SubscriberConnections {
+        &self.connections
+    }
+}
+
+impl PublisherStream<RawMessage> {
+    /// Sends a message that is already serialized, without its length prefix
+    pub async fn send(&self, data: &[u8]) -> Result<(), PublisherSendError> {
+        self.latch.send(&self.sender, super::encode_packet(data));
+        Ok(())
+    }
 }
 
 impl<T: Message> PublisherStream<T> {
@@ -259,9 +522,7 @@ impl<T: Message> PublisherStream<T> {
             .encode_vec()
             .map_err(PublisherSendError::EncodingError)?;
 
-        // TODO: latching??
-
-        let _ = self.sender.send(bytes);
+        self.latch.send(&self.sender, bytes);
         Ok(())
     }
 }
@@ -0,0 +1,5 @@
+mod client;
+mod server;
+
+pub use client::{probe as probe_service, ServiceCallError, ServiceConnection};
+pub use server::{Service, ServiceError};
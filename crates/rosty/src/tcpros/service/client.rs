@@ -0,0 +1,164 @@
+use crate::node::NameError;
+use crate::rosxmlrpc::ResponseError;
+use crate::tcpros::{header, read_packet, ServicePair};
+use futures::future;
+use rosty_msg::RosMsg;
+use std::collections::HashMap;
+use std::io;
+use std::task::Poll;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[derive(Debug, Fail)]
+pub enum ServiceCallError {
+    #[fail(display = "failed to look up service")]
+    LookupError(#[fail(cause)] ResponseError),
+
+    #[fail(display = "invalid service uri '{}'", 0)]
+    InvalidUri(String),
+
+    #[fail(display = "transport error")]
+    TransportError(#[fail(cause)] io::Error),
+
+    #[fail(display = "invalid header: {}", 0)]
+    InvalidHeader(header::InvalidHeaderError),
+
+    #[fail(display = "service call failed: {}", 0)]
+    ServiceFailure(String),
+
+    #[fail(display = "timed out waiting for service")]
+    Timeout,
+
+    #[fail(display = "{}", 0)]
+    InvalidName(NameError),
+}
+
+impl From<io::Error> for ServiceCallError {
+    fn from(e: io::Error) -> Self {
+        ServiceCallError::TransportError(e)
+    }
+}
+
+impl From<header::InvalidHeaderError> for ServiceCallError {
+    fn from(e: header::InvalidHeaderError) -> Self {
+        ServiceCallError::InvalidHeader(e)
+    }
+}
+
+/// A connection to a service server over which requests can be sent.
+pub struct ServiceConnection {
+    stream: TcpStream,
+}
+
+impl ServiceConnection {
+    /// Connects to the service server at the given `rosrpc://` uri and performs the handshake. If
+    /// `persistent` is set the server keeps the connection open after a request was handled.
+    pub async fn connect<S: ServicePair>(
+        uri: &str,
+        caller_id: &str,
+        service: &str,
+        persistent: bool,
+    ) -> Result<Self, ServiceCallError> {
+        let mut stream = TcpStream::connect(parse_service_uri(uri)?).await?;
+        let mut fields = handshake_fields::<S>(caller_id, service);
+        if persistent {
+            fields.insert(String::from("persistent"), String::from("1"));
+        }
+        handshake::<S, _>(&mut stream, &fields).await?;
+        Ok(ServiceConnection { stream })
+    }
+
+    /// Sends a request to the service server and waits for its response
+    pub async fn call<S: ServicePair>(
+        &mut self,
+        request: &S::Request,
+    ) -> Result<S::Response, ServiceCallError> {
+        self.send::<S>(request).await?;
+        self.receive::<S>().await
+    }
+
+    /// Writes a request to the service server
+    pub async fn send<S: ServicePair>(&mut self, request: &S::Request) -> Result<(), io::Error> {
+        self.stream.write_all(&request.encode_vec()?).await
+    }
+
+    /// Waits for the response to a request that was sent before
+    pub async fn receive<S: ServicePair>(&mut self) -> Result<S::Response, ServiceCallError> {
+        // The response starts with a single byte that indicates if the call succeeded
+        let ok = self.stream.read_u8().await?;
+        let packet = read_packet(&mut self.stream).await?;
+        if ok != 0 {
+            Ok(S::Response::decode_slice(&packet)?)
+        } else {
+            Err(ServiceCallError::ServiceFailure(String::decode(
+                &packet[..],
+            )?))
+        }
+    }
+
+    /// Returns true if the server closed the connection. The stream is only peeked at, so data
+    /// the server sent is left for the next read. Does not wait for the stream to become readable.
+    pub async fn is_closed(&mut self) -> bool {
+        let stream = &mut self.stream;
+        future::poll_fn(|cx| {
+            let mut buffer = [0u8; 1];
+            Poll::Ready(match stream.poll_peek(cx, &mut buffer) {
+                Poll::Ready(Ok(0)) | Poll::Ready(Err(_)) => true,
+                Poll::Ready(Ok(_)) | Poll::Pending => false,
+            })
+        })
+        .await
+    }
+}
+
+/// Checks whether the service server at the given `rosrpc://` uri accepts connections by sending
+/// a probe header. The server answers the probe with its own header and closes the connection.
+pub async fn probe(uri: &str, caller_id: &str, service: &str) -> Result<(), ServiceCallError> {
+    let mut stream = TcpStream::connect(parse_service_uri(uri)?).await?;
+    let mut fields = HashMap::<String, String>::new();
+    fields.insert(String::from("callerid"), String::from(caller_id));
+    fields.insert(String::from("service"), String::from(service));
+    fields.insert(String::from("md5sum"), String::from("*"));
+    fields.insert(String::from("probe"), String::from("1"));
+    header::encode_and_write(&mut stream, &fields).await?;
+    let fields = header::read_and_decode(&mut stream).await?;
+    match fields.get("error") {
+        Some(error) => Err(ServiceCallError::ServiceFailure(error.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Splits a `rosrpc://host:port` uri into its hostname and port
+fn parse_service_uri(uri: &str) -> Result<(&str, u16), ServiceCallError> {
+    let invalid_uri = || ServiceCallError::InvalidUri(uri.to_owned());
+    let address = uri.strip_prefix("rosrpc://").ok_or_else(invalid_uri)?;
+    let address = address.trim_end_matches('/');
+    let separator = address.rfind(':').ok_or_else(invalid_uri)?;
+    let port = address[separator + 1..]
+        .parse()
+        .map_err(|_| invalid_uri())?;
+    Ok((&address[..separator], port))
+}
+
+/// Returns the header fields a service client sends to the server
+fn handshake_fields<S: ServicePair>(caller_id: &str, service: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::<String, String>::new();
+    fields.insert(String::from("callerid"), String::from(caller_id));
+    fields.insert(String::from("service"), String::from(service));
+    fields.insert(String::from("md5sum"), S::md5sum());
+    fields
+}
+
+/// Writes the request header to the service server and validates the response header
+async fn handshake<S: ServicePair, U: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: &mut U,
+    fields: &HashMap<String, String>,
+) -> Result<(), ServiceCallError> {
+    header::encode_and_write(&mut stream, fields).await?;
+    let fields = header::read_and_decode(&mut stream).await?;
+    if let Some(error) = fields.get("error") {
+        return Err(ServiceCallError::ServiceFailure(error.clone()));
+    }
+    header::match_field(&fields, "md5sum", &S::md5sum())?;
+    Ok(())
+}
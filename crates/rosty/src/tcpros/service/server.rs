@@ -0,0 +1,298 @@
+use crate::node::NameError;
+use crate::rosxmlrpc::ResponseError;
+use crate::shutdown_token::ShutdownToken;
+use crate::tcpros::{header, read_packet, ServicePair};
+use futures::StreamExt;
+use rosty_msg::RosMsg;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::io::ErrorKind;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tracing_futures::Instrument;
+
+#[derive(Debug, Fail)]
+pub enum ServiceError {
+    #[fail(display = "bind error")]
+    BindError(#[fail(cause)] std::io::Error),
+
+    #[fail(display = "could not get local address")]
+    LocalAddressError(#[fail(cause)] std::io::Error),
+
+    #[fail(display = "service '{}' is already advertised by this node", service)]
+    DuplicateService { service: String },
+
+    #[fail(display = "registration error")]
+    RegistrationError(#[fail(cause)] ResponseError),
+
+    #[fail(display = "{}", 0)]
+    InvalidName(NameError),
+}
+
+#[derive(Debug, Fail)]
+enum ServiceClientHandshakeError {
+    #[fail(display = "transport error")]
+    TransportError(#[fail(cause)] std::io::Error),
+
+    #[fail(display = "invalid header: {}", 0)]
+    InvalidHeader(header::InvalidHeaderError),
+}
+
+impl From<std::io::Error> for ServiceClientHandshakeError {
+    fn from(e: std::io::Error) -> Self {
+        ServiceClientHandshakeError::TransportError(e)
+    }
+}
+
+impl From<header::InvalidHeaderError> for ServiceClientHandshakeError {
+    fn from(e: header::InvalidHeaderError) -> Self {
+        ServiceClientHandshakeError::InvalidHeader(e)
+    }
+}
+
+/// A TCPROS service server. Accepts connections from service clients and answers their requests
+/// by invoking a handler until the service is dropped.
+pub struct Service {
+    pub port: u16,
+    shutdown_token: ShutdownToken,
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        self.shutdown_token.shutdown();
+    }
+}
+
+impl Service {
+    pub async fn new<S, F, R, U>(
+        address: U,
+        service: &str,
+        caller_id: &str,
+        handler: F,
+    ) -> Result<Service, ServiceError>
+    where
+        S: ServicePair,
+        F: Fn(S::Request) -> R + Send + Sync + 'static,
+        R: Future<Output = Result<S::Response, String>> + Send + 'static,
+        U: ToSocketAddrs,
+    {
+        let shutdown_token = ShutdownToken::default();
+        let mut listener = TcpListener::bind(address)
+            .await
+            .map_err(ServiceError::BindError)?;
+        let port = listener
+            .local_addr()
+            .map_err(ServiceError::LocalAddressError)?
+            .port();
+
+        let service_str = service.to_owned();
+        let caller_id_str = caller_id.to_owned();
+        let handler = Arc::new(handler);
+
+        // Accept connections until the service is shut down
+        let accept_shutdown_token = shutdown_token.clone();
+        let client_shutdown_token = shutdown_token.clone();
+        tokio::spawn(async move {
+            let accept_future = listener
+                .incoming()
+                .for_each_concurrent(None, move |stream| {
+                    let service_str = service_str.clone();
+                    let caller_id_str = caller_id_str.clone();
+                    let handler = handler.clone();
+                    let shutdown_token = client_shutdown_token.clone();
+                    async move {
+                        match stream {
+                            Ok(stream) => {
+                                // The client may already have disconnected
+                                let remote = match stream.peer_addr() {
+                                    Ok(remote) => remote.to_string(),
+                                    Err(e) => {
+                                        debug!("incoming connection closed: {}", e);
+                                        return;
+                                    }
+                                };
+                                let span = tracing::info_span!(
+                                    "service",
+                                    service = service_str.as_str(),
+                                    remote = remote.as_str()
+                                );
+                                tokio::spawn(
+                                    async move {
+                                        process_client::<S, _, _, _>(
+                                            &service_str,
+                                            stream,
+                                            &caller_id_str,
+                                            handler,
+                                            shutdown_token,
+                                        )
+                                        .await;
+                                    }
+                                    .instrument(span),
+                                );
+                            }
+                            Err(e) => error!("incoming connection failed: {}", e),
+                        };
+                    }
+                });
+            tokio::select!(
+                _ = accept_future => {},
+                _ = accept_shutdown_token => {});
+        });
+
+        Ok(Service {
+            port,
+            shutdown_token,
+        })
+    }
+}
+
+/// Handles a single connection from a service client. After the handshake, requests are read
+/// from the stream and answered until the client disconnects. Non-persistent connections are
+/// closed after the first response. All connections are closed when the service shuts down.
+async fn process_client<S, F, R, U>(
+    service: &str,
+    mut stream: U,
+    caller_id: &str,
+    handler: Arc<F>,
+    shutdown_token: ShutdownToken,
+) where
+    S: ServicePair,
+    F: Fn(S::Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<S::Response, String>> + Send + 'static,
+    U: AsyncWrite + AsyncRead + Send + Unpin,
+{
+    let fields = match handshake::<S, _>(&mut stream, caller_id, service).await {
+        Ok(fields) => fields,
+        Err(e) => {
+            error!("handshake error: {}, aborting..", e);
+            if let ServiceClientHandshakeError::InvalidHeader(_) = e {
+                // Tell the client why the connection is closed, like roscpp and rospy do
+                if let Err(e) = header::write_error(&mut stream, &e.to_string()).await {
+                    debug!("error sending handshake error: {}", e);
+                }
+            }
+            return;
+        }
+    };
+
+    // A probe only checks whether the service is available, no requests will follow
+    if fields.get("probe").map(String::as_str) == Some("1") {
+        debug!("probed");
+        return;
+    }
+
+    let persistent = fields.get("persistent").map(String::as_str) == Some("1");
+    let client_caller_id = fields.get("callerid").cloned().unwrap_or_default();
+
+    let serve = async {
+        loop {
+            let packet = match read_packet(&mut stream).await {
+                Ok(packet) => packet,
+                Err(e) => {
+                    match e.kind() {
+                        ErrorKind::UnexpectedEof => debug!("client disconnected"),
+                        _ => error!("error reading request: {}, disconnecting..", e),
+                    }
+                    return;
+                }
+            };
+
+            let response = match S::Request::decode_slice(&packet) {
+                Ok(request) => handler(request).await,
+                Err(e) => Err(format!("failed to decode request: {}", e)),
+            };
+
+            if let Err(e) = write_response(&mut stream, response).await {
+                error!("error sending response: {}, disconnecting..", e);
+                return;
+            }
+
+            if !persistent {
+                return;
+            }
+        }
+    };
+
+    // Stop serving the client as soon as the service is dropped
+    async {
+        tokio::select!(
+            _ = serve => {},
+            _ = shutdown_token => debug!("service shut down, disconnecting.."));
+    }
+    .instrument(tracing::info_span!(
+        "caller",
+        id = client_caller_id.as_str()
+    ))
+    .await
+}
+
+/// Writes the response of a service call to the `stream`. A response consists of a single byte
+/// that indicates whether the call succeeded, followed by either the serialized response message
+/// or the error string.
+async fn write_response<T: RosMsg, U: AsyncWrite + Unpin>(
+    stream: &mut U,
+    response: Result<T, String>,
+) -> Result<(), io::Error> {
+    let mut data = Vec::with_capacity(128);
+    match response {
+        Ok(response) => {
+            data.push(1u8);
+            data.extend(response.encode_vec()?);
+        }
+        Err(message) => {
+            data.push(0u8);
+            message.encode(&mut data)?;
+        }
+    }
+    stream.write_all(&data).await
+}
+
+async fn handshake<S: ServicePair, U: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut U,
+    caller_id: &str,
+    service: &str,
+) -> Result<HashMap<String, String>, ServiceClientHandshakeError> {
+    let fields = read_handshake_request::<S, U>(stream, service).await?;
+    write_handshake_response::<S, U>(stream, caller_id).await?;
+    Ok(fields)
+}
+
+async fn read_handshake_request<S: ServicePair, U: AsyncRead + Unpin>(
+    mut stream: &mut U,
+    service: &str,
+) -> Result<HashMap<String, String>, ServiceClientHandshakeError> {
+    let fields = header::read_and_decode(&mut stream).await?;
+    if fields.get("md5sum").map(String::as_str) != Some("*") {
+        header::match_field(&fields, "md5sum", &S::md5sum())?;
+    }
+    header::match_field(&fields, "service", service)?;
+    if !fields.contains_key("callerid") {
+        return Err(header::InvalidHeaderError::MissingField("callerid".into()).into());
+    }
+    Ok(fields)
+}
+
+/// Writes a header that only contains the reason why the handshake of a client was rejected
+async fn write_handshake_response<S: ServicePair, U: AsyncWrite + Unpin>(
+    mut stream: &mut U,
+    caller_id: &str,
+) -> Result<(), ServiceClientHandshakeError> {
+    let service_type = S::msg_type();
+    let mut fields = HashMap::<String, String>::new();
+    fields.insert(String::from("callerid"), caller_id.into());
+    fields.insert(String::from("md5sum"), S::md5sum());
+    fields.insert(String::from("type"), service_type.clone());
+    fields.insert(
+        String::from("request_type"),
+        format!("{}Request", service_type),
+    );
+    fields.insert(
+        String::from("response_type"),
+        format!("{}Response", service_type),
+    );
+    header::encode_and_write(&mut stream, &fields)
+        .await
+        .map_err(Into::into)
+}
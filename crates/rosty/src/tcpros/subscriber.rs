@@ -1,20 +1,29 @@
-use super::Message;
+use super::{next_connection_id, ConnectionInfo, Counters, Direction, Message, MessageDescription};
+use crate::rosxmlrpc;
+use crate::rosxmlrpc::{Response, ResponseError};
+use crate::shutdown_token::ShutdownToken;
 use crate::tcpros::header;
 use crate::Topic;
 use futures::stream::StreamExt;
-use futures::TryFutureExt;
 use rosty_msg::RosMsg;
-use std::collections::{BTreeSet, HashMap};
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io;
 use std::io::ErrorKind;
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
-use tokio::net::ToSocketAddrs;
 use tokio::sync::mpsc;
 use tracing_futures::Instrument;
 
+/// The delay before the first attempt to reconnect to a publisher
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// The maximum delay between attempts to reconnect to a publisher
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
 #[derive(Fail, Debug)]
 enum SubscriberError {
     #[fail(display = "transport error")]
@@ -22,15 +31,28 @@ enum SubscriberError {
 
     #[fail(display = "invalid header: {}", 0)]
     InvalidHeader(header::InvalidHeaderError),
-}
 
-#[derive(Fail, Debug)]
-pub enum PublisherConnectError {
-    #[fail(display = "connection queue is full")]
-    ConnectionQueueFull,
+    #[fail(display = "error calling 'requestTopic' on the publisher: {}", 0)]
+    RequestTopicError(ResponseError),
 
-    #[fail(display = "transport error")]
-    TransportError(io::Error),
+    #[fail(display = "publisher responded with a non-TCPROS protocol: {}", 0)]
+    ProtocolMismatch(String),
+
+    #[fail(display = "publisher rejected the connection: {}", 0)]
+    Rejected(String),
+}
+
+impl SubscriberError {
+    /// Returns true if connecting again cannot succeed because the publisher and subscriber do
+    /// not agree on what is sent over the connection.
+    fn is_permanent(&self) -> bool {
+        match self {
+            SubscriberError::TransportError(_) | SubscriberError::RequestTopicError(_) => false,
+            SubscriberError::InvalidHeader(_)
+            | SubscriberError::ProtocolMismatch(_)
+            | SubscriberError::Rejected(_) => true,
+        }
+    }
 }
 
 impl From<io::Error> for SubscriberError {
@@ -45,72 +67,259 @@ impl From<header::InvalidHeaderError> for SubscriberError {
     }
 }
 
-impl From<io::Error> for PublisherConnectError {
-    fn from(err: io::Error) -> Self {
-        PublisherConnectError::TransportError(err)
-    }
-}
+/// A packet received from a publisher
+struct Packet {
+    /// The uri of the publisher
+    publisher: Arc<String>,
 
-/// A struct with raw message info received from a publisher
-struct RawMessage {
     /// The publisher that send the message
     caller_id: Arc<String>,
 
-    /// The data of the message
+    /// The connection header of the publisher
+    header: Arc<HashMap<String, String>>,
+
+    /// The data of the message, including its length
+    data: Vec<u8>,
+
+    /// The counters of the connection the packet was received on
+    counters: Arc<Counters>,
+}
+
+/// A message that was received without knowing its type at compile time
+#[derive(Clone, Debug)]
+pub struct RawMessage {
+    /// The connection header sent by the publisher. Contains the `type`, `md5sum`,
+    /// `message_definition`, `callerid` and `latching` fields.
+    pub header: Arc<HashMap<String, String>>,
+
+    /// The serialized message, without its length
+    pub data: Vec<u8>,
+}
+
+/// A type of message that can be received by a `Subscriber`
+pub trait SubscriberMessage: Clone + Send + 'static {
+    /// Returns the description that is sent to publishers during the handshake
+    fn description() -> MessageDescription;
+
+    /// Constructs a message from a packet received from a publisher with the given `header`
+    fn decode(header: &Arc<HashMap<String, String>>, packet: Vec<u8>) -> io::Result<Self>;
+}
+
+impl<T: Message> SubscriberMessage for T {
+    fn description() -> MessageDescription {
+        MessageDescription::of::<T>()
+    }
+
+    fn decode(_header: &Arc<HashMap<String, String>>, packet: Vec<u8>) -> io::Result<Self> {
+        RosMsg::decode_slice(&packet)
+    }
+}
+
+impl SubscriberMessage for RawMessage {
+    fn description() -> MessageDescription {
+        MessageDescription::new("*", "*", "")
+    }
+
+    fn decode(header: &Arc<HashMap<String, String>>, mut packet: Vec<u8>) -> io::Result<Self> {
+        Ok(RawMessage {
+            header: header.clone(),
+            data: packet.split_off(std::mem::size_of::<u32>()),
+        })
+    }
+}
+
+/// The last message of a latching publisher
+struct LatchedMessage {
+    caller_id: Arc<String>,
+    header: Arc<HashMap<String, String>>,
     data: Vec<u8>,
 }
 
+/// The channels of handles that receive messages of type `T`
+type Channels<T> = Vec<(usize, mpsc::Sender<IncomingMessage<T>>)>;
+
+/// Sends a copy of the `message` to every channel and drops the channels that are closed. Returns
+/// the number of channels that dropped the message because their queue was full.
+fn send_to_all<T: Clone>(channels: &mut Channels<T>, caller_id: &str, message: T) -> u64 {
+    let mut closed = Vec::new();
+    let mut drops = 0;
+    for (id, sender) in channels.iter_mut() {
+        match sender.try_send((caller_id.to_owned(), message.clone())) {
+            Ok(_) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                error!("queue is full!");
+                drops += 1;
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => closed.push(*id),
+        }
+    }
+    channels.retain(|(id, _)| !closed.contains(id));
+    drops
+}
+
+/// Type erased access to the channels of the handles that receive decoded messages
+trait TypedChannels: Send {
+    /// Decodes a packet and sends it to every channel. Returns the number of channels that dropped
+    /// the message because their queue was full.
+    fn send(
+        &mut self,
+        caller_id: &str,
+        header: &Arc<HashMap<String, String>>,
+        data: Vec<u8>,
+    ) -> io::Result<u64>;
+
+    /// Removes the channel with the given id. Returns true if no channels remain.
+    fn remove(&mut self, id: usize) -> bool;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: SubscriberMessage> TypedChannels for Channels<T> {
+    fn send(
+        &mut self,
+        caller_id: &str,
+        header: &Arc<HashMap<String, String>>,
+        data: Vec<u8>,
+    ) -> io::Result<u64> {
+        let message = T::decode(header, data)?;
+        Ok(send_to_all(self, caller_id, message))
+    }
+
+    fn remove(&mut self, id: usize) -> bool {
+        self.retain(|(channel_id, _)| *channel_id != id);
+        self.is_empty()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The channels of all handles that receive the messages of a subscription. Handles that receive
+/// `RawMessage`s share the subscription with handles of any type, the other handles all receive
+/// messages of the same type.
+struct Receivers {
+    next_id: usize,
+
+    /// The channels of the handles that receive serialized messages
+    raw: Channels<RawMessage>,
+
+    /// The channels of the handles that receive decoded messages, `None` if there are none
+    typed: Option<Box<dyn TypedChannels>>,
+
+    /// The last message of every latching publisher, by the uri of the publisher. Receivers that
+    /// are added later receive these first, like they would when they had their own connection.
+    latched: BTreeMap<String, LatchedMessage>,
+}
+
+impl Receivers {
+    /// Remembers the packet if it was sent by a latching publisher
+    fn latch(&mut self, packet: &Packet) {
+        if packet.header.get("latching").map(String::as_str) == Some("1") {
+            self.latched.insert(
+                packet.publisher.to_string(),
+                LatchedMessage {
+                    caller_id: packet.caller_id.clone(),
+                    header: packet.header.clone(),
+                    data: packet.data.clone(),
+                },
+            );
+        }
+    }
+
+    /// Sends a packet to every receiver, the raw receivers first. Returns the number of receivers
+    /// that dropped the message because their queue was full.
+    fn send(&mut self, packet: Packet) -> u64 {
+        let mut drops = 0;
+        if !self.raw.is_empty() {
+            match RawMessage::decode(&packet.header, packet.data.clone()) {
+                Ok(message) => drops += send_to_all(&mut self.raw, &packet.caller_id, message),
+                Err(err) => error!("failed to decode message: {}", err),
+            }
+        }
+        if let Some(typed) = self.typed.as_mut() {
+            match typed.send(&packet.caller_id, &packet.header, packet.data) {
+                Ok(typed_drops) => drops += typed_drops,
+                Err(err) => error!("failed to decode message: {}", err),
+            }
+        }
+        drops
+    }
+}
+
 /// A subscriber on a ros topic. Manages connecting to publishers and receiving data from them.
+/// Received messages are decoded once and passed on to every receiver of the subscription.
 pub struct Subscriber {
-    /// Sender end of a channel that receives updates about publishers
-    publisher_tx: mpsc::Sender<SocketAddr>,
+    /// Sender end of a channel that starts a link for every new publisher
+    link_tx: mpsc::UnboundedSender<(String, ShutdownToken, Arc<AtomicBool>, Arc<Counters>)>,
 
-    /// A set of publishers this subscriber is connecting or connected to.
-    connected_publishers: BTreeSet<String>,
+    /// The links to all publishers of the topic, by the uri of the publisher
+    links: BTreeMap<String, PublisherLink>,
 
     /// The topic that this `Subscriber` subscribes to
     topic: Topic,
+
+    /// The description of the messages that is sent to new publishers
+    description: Arc<Mutex<Arc<MessageDescription>>>,
+
+    /// The receivers of the subscription, shared with the task that decodes messages
+    receivers: Arc<Mutex<Receivers>>,
+}
+
+/// The connection to a single publisher. The link keeps reconnecting to the publisher until it is
+/// dropped.
+struct PublisherLink {
+    id: usize,
+    shutdown_token: ShutdownToken,
+
+    /// Whether the link is currently connected to the publisher
+    connected: Arc<AtomicBool>,
+
+    /// The data received over the link, across reconnects
+    counters: Arc<Counters>,
+}
+
+impl Drop for PublisherLink {
+    fn drop(&mut self) {
+        self.shutdown_token.shutdown();
+    }
 }
 
 pub type IncomingMessage<T> = (String, T);
 
 impl Subscriber {
-    pub fn new<T>(
-        caller_id: &str,
-        topic: &str,
-        queue_size: usize,
-    ) -> (Self, mpsc::Receiver<IncomingMessage<T>>)
+    pub fn new<T>(caller_id: &str, topic: &str) -> Self
     where
-        T: Message,
+        T: SubscriberMessage,
     {
-        let (mut topic_tx, topic_rx) = mpsc::channel(queue_size);
-        let (data_tx, mut data_rx) = mpsc::channel(queue_size);
-        let (publisher_tx, mut publisher_rx) = mpsc::channel(8);
+        let description = Arc::new(Mutex::new(Arc::new(T::description())));
+        let (data_tx, mut data_rx) = mpsc::channel(8);
+        let (link_tx, mut link_rx) = mpsc::unbounded_channel();
 
         let caller_id = String::from(caller_id);
         let topic_name = String::from(topic);
 
         let data_sender = data_tx;
+        let link_description = description.clone();
         tokio::spawn(
             async move {
-                while let Some(addr) = publisher_rx.next().await {
-                    let data_tx = data_sender.clone();
-                    tokio::spawn(
-                        connect_to_publisher::<T>(
-                            addr,
-                            caller_id.clone(),
-                            topic_name.clone(),
-                            data_tx,
-                        )
-                        .map_err(|e| {
-                            error!("error connecting to publisher: {}", e);
-                            e
-                        })
-                        .instrument(tracing::info_span!(
-                            "connect_to_publisher",
-                            addr = tracing::field::display(addr)
-                        )),
+                while let Some((publisher, shutdown_token, connected, counters)) =
+                    link_rx.next().await
+                {
+                    let span = tracing::info_span!(
+                        "publisher_link",
+                        publisher = tracing::field::display(&publisher)
                     );
+                    let link = LinkContext {
+                        publisher,
+                        caller_id: caller_id.clone(),
+                        topic: topic_name.clone(),
+                        description: link_description.lock().unwrap().clone(),
+                        data_tx: data_sender.clone(),
+                        connected,
+                        counters,
+                    };
+                    tokio::spawn(publisher_link(link, shutdown_token).instrument(span));
                 }
             }
             .instrument(tracing::info_span!(
@@ -119,104 +328,320 @@ impl Subscriber {
             )),
         );
 
+        let receivers = Arc::new(Mutex::new(Receivers {
+            next_id: 0,
+            raw: Vec::new(),
+            typed: None,
+            latched: BTreeMap::new(),
+        }));
+
+        let data_receivers = receivers.clone();
         tokio::spawn(
             async move {
-                while let Some(buffer) = data_rx.recv().await {
-                    match RosMsg::decode_slice(&buffer.data) {
-                        Ok(value) => {
-                            if topic_tx
-                                .try_send(((*buffer.caller_id).clone(), value))
-                                .is_err()
-                            {
-                                error!("queue is full!");
-                            }
-                        }
-                        Err(err) => error!("failed to decode message: {}", err),
-                    }
+                while let Some(packet) = data_rx.recv().await {
+                    let mut receivers = data_receivers.lock().unwrap();
+                    receivers.latch(&packet);
+                    let counters = packet.counters.clone();
+                    counters.dropped(receivers.send(packet));
                 }
             }
             .instrument(tracing::info_span!("handle_data", topic = topic)),
         );
 
-        (
-            Subscriber {
-                publisher_tx,
-                connected_publishers: Default::default(),
-                topic: Topic {
-                    name: topic.to_owned(),
-                    data_type: T::msg_type(),
-                },
+        Subscriber {
+            link_tx,
+            links: Default::default(),
+            topic: Topic {
+                name: topic.to_owned(),
+                data_type: T::description().msg_type().to_owned(),
             },
-            topic_rx,
-        )
+            description,
+            receivers,
+        }
     }
 
-    // /// Returns the number of publishers
-    // pub fn publisher_count(&self) -> usize {
-    //     self.connected_publishers.len()
-    // }
-
-    // /// Returns an iterator over all publishers
-    // pub fn publishers(&self) -> impl Iterator<Item = String> + '_ {
-    //     self.connected_publishers.iter().cloned()
-    // }
+    /// Returns the topic of this subscriber
+    pub fn topic(&self) -> &Topic {
+        &self.topic
+    }
 
-    /// Connect to node that publishes the subscribed topic
-    pub async fn connect_to<U: ToSocketAddrs>(
+    /// Adds a new receiver of the messages of this subscription. The receiver first receives the
+    /// last messages of the latching publishers. A `RawMessage` receiver can join any subscription
+    /// and a subscription that only has `RawMessage` receivers adopts the type of the first other
+    /// receiver. Returns the id of the receiver and the receiving end of its channel, or `None` if
+    /// the subscription receives messages of another type than `T`.
+    pub fn add_receiver<T: SubscriberMessage>(
         &mut self,
-        publisher: &str,
-        addresses: U,
-    ) -> Result<(), PublisherConnectError> {
-        for address in addresses.to_socket_addrs().await? {
-            info!(topic=?self.topic, publisher=publisher, address=tracing::field::display(address), "connecting");
-            self.publisher_tx
-                .send(address)
-                .await
-                .map_err(|_| PublisherConnectError::ConnectionQueueFull)?;
+        queue_size: usize,
+    ) -> Option<(usize, mpsc::Receiver<IncomingMessage<T>>)> {
+        let mut receivers = self.receivers.lock().unwrap();
+        let (mut sender, receiver) = mpsc::channel(queue_size);
+        for latched in receivers.latched.values() {
+            match T::decode(&latched.header, latched.data.clone()) {
+                Ok(value) => {
+                    let _ = sender.try_send((latched.caller_id.to_string(), value));
+                }
+                Err(err) => error!("failed to decode latched message: {}", err),
+            }
+        }
+        let id = receivers.next_id;
+
+        let sender: Box<dyn Any> = Box::new(sender);
+        let adopted = match sender.downcast::<mpsc::Sender<IncomingMessage<RawMessage>>>() {
+            Ok(sender) => {
+                receivers.raw.push((id, *sender));
+                false
+            }
+            Err(sender) => {
+                let sender = *sender
+                    .downcast::<mpsc::Sender<IncomingMessage<T>>>()
+                    .expect("sender has the type of the receiver");
+                match receivers.typed.as_mut() {
+                    Some(typed) => {
+                        typed
+                            .as_any_mut()
+                            .downcast_mut::<Channels<T>>()?
+                            .push((id, sender));
+                        false
+                    }
+                    None => {
+                        receivers.typed = Some(Box::new(vec![(id, sender)]));
+                        true
+                    }
+                }
+            }
+        };
+        receivers.next_id += 1;
+        drop(receivers);
+
+        if adopted {
+            self.set_description(T::description());
         }
-        self.connected_publishers.insert(publisher.to_owned());
-        Ok(())
+        Some((id, receiver))
     }
 
-    /// Returns true if the subscriber is connecto to the given publisher
-    pub fn is_connected_to(&self, publisher: &str) -> bool {
-        self.connected_publishers.contains(publisher)
+    /// Removes the receiver with the given id. Returns true if no receivers remain.
+    pub fn remove_receiver(&mut self, id: usize) -> bool {
+        let mut receivers = self.receivers.lock().unwrap();
+        receivers.raw.retain(|(receiver_id, _)| *receiver_id != id);
+        let typed_removed = match receivers.typed.as_mut() {
+            Some(typed) => typed.remove(id),
+            None => false,
+        };
+        if typed_removed {
+            receivers.typed = None;
+        }
+        let is_empty = receivers.raw.is_empty() && receivers.typed.is_none();
+        drop(receivers);
+
+        // Without typed receivers any type of message is accepted again
+        if typed_removed && !is_empty {
+            self.set_description(RawMessage::description());
+        }
+        is_empty
+    }
+
+    /// Changes the type of the messages of the subscription. New links to publishers use the
+    /// description in their handshake.
+    fn set_description(&mut self, description: MessageDescription) {
+        self.topic.data_type = description.msg_type().to_owned();
+        *self.description.lock().unwrap() = Arc::new(description);
+    }
+
+    /// Returns the links to the publishers of the subscribed topic. Links that gave up because
+    /// the publisher rejected the handshake are not included.
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        self.links
+            .iter()
+            .filter(|(_, link)| !link.shutdown_token.is_awaiting_shutdown())
+            .map(|(publisher, link)| ConnectionInfo {
+                id: link.id,
+                destination: publisher.clone(),
+                direction: Direction::Inbound,
+                topic: self.topic.name.clone(),
+                connected: link.connected.load(Ordering::Relaxed),
+                stats: link.counters.snapshot(),
+            })
+            .collect()
+    }
+
+    /// Updates the publishers of the subscribed topic to the given set. Links to publishers that
+    /// are no longer in the set are closed and links to new publishers are started.
+    pub fn set_publishers<I: IntoIterator<Item = String>>(&mut self, publishers: I) {
+        let publishers: BTreeSet<String> = publishers.into_iter().collect();
+
+        // Close the links to publishers that are gone
+        let topic = &self.topic.name;
+        let receivers = &self.receivers;
+        self.links.retain(|publisher, link| {
+            let retain = publishers.contains(publisher);
+            if !retain {
+                receivers.lock().unwrap().latched.remove(publisher);
+                info!(
+                    topic = topic.as_str(),
+                    publisher = publisher.as_str(),
+                    connected = link.connected.load(Ordering::Relaxed),
+                    "publisher removed"
+                );
+            }
+            retain
+        });
+
+        // Start links to the new publishers
+        for publisher in publishers {
+            if self.links.contains_key(&publisher) {
+                continue;
+            }
+            info!(
+                topic = self.topic.name.as_str(),
+                publisher = publisher.as_str(),
+                "connecting"
+            );
+            let link = PublisherLink {
+                id: next_connection_id(),
+                shutdown_token: ShutdownToken::default(),
+                connected: Arc::new(AtomicBool::new(false)),
+                counters: Arc::new(Counters::default()),
+            };
+            if self
+                .link_tx
+                .send((
+                    publisher.clone(),
+                    link.shutdown_token.clone(),
+                    link.connected.clone(),
+                    link.counters.clone(),
+                ))
+                .is_err()
+            {
+                error!("failed to start link to publisher {}", publisher);
+                continue;
+            }
+            self.links.insert(publisher, link);
+        }
     }
 }
 
-/// Connects to the publisher that is listening at the specified address
-async fn connect_to_publisher<T: Message>(
-    addr: SocketAddr,
+/// Describes why a connection to a publisher ended without an error
+enum LinkClosed {
+    /// The publisher closed the connection or the connection was lost
+    Disconnected,
+
+    /// The subscription is no longer interested in messages
+    Cancelled,
+}
+
+/// Everything a link needs to connect to a single publisher of a topic
+struct LinkContext {
+    /// The uri of the publisher
+    publisher: String,
+
+    /// The name of the subscribing node
     caller_id: String,
+
+    /// The name of the topic
     topic: String,
-    mut data_tx: mpsc::Sender<RawMessage>,
-) -> Result<(), SubscriberError> {
+
+    /// The description of the messages the subscriber expects
+    description: Arc<MessageDescription>,
+
+    /// The channel received packets are sent to
+    data_tx: mpsc::Sender<Packet>,
+
+    /// Set while a connection with the publisher is established
+    connected: Arc<AtomicBool>,
+
+    /// Statistics of the connection
+    counters: Arc<Counters>,
+}
+
+/// Keeps a connection to the publisher until the `shutdown_token` signals. When the connection
+/// fails or is lost the link retries with an increasing delay, unless the publisher and the
+/// subscriber do not agree on the message type. In that case the link shuts itself down.
+async fn publisher_link(link: LinkContext, shutdown_token: ShutdownToken) {
+    let mut delay = MIN_RECONNECT_DELAY;
+    loop {
+        let result = tokio::select!(
+            result = connect_to_publisher(&link) => result,
+            _ = shutdown_token.clone() => break
+        );
+        link.connected.store(false, Ordering::Relaxed);
+
+        match result {
+            Ok(LinkClosed::Cancelled) => break,
+            Ok(LinkClosed::Disconnected) => delay = MIN_RECONNECT_DELAY,
+            Err(e) if e.is_permanent() => {
+                error!("error connecting to publisher: {}, giving up", e);
+                shutdown_token.shutdown();
+                break;
+            }
+            Err(e) => warn!("error connecting to publisher: {}", e),
+        }
+
+        // Wait a while before reconnecting to the publisher
+        info!(delay = ?delay, "reconnecting");
+        tokio::select!(
+            _ = tokio::time::delay_for(delay) => {},
+            _ = shutdown_token.clone() => break
+        );
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+    link.connected.store(false, Ordering::Relaxed);
+    info!("link closed");
+}
+
+/// Connects to the publisher at the specified uri and receives data from it until the connection
+/// is closed.
+async fn connect_to_publisher(link: &LinkContext) -> Result<LinkClosed, SubscriberError> {
+    let LinkContext {
+        publisher,
+        caller_id,
+        topic,
+        description,
+        connected,
+        counters,
+        ..
+    } = link;
+    let mut data_tx = link.data_tx.clone();
+
+    // Ask the publisher where to connect to
+    let (protocol, hostname, port) = request_topic(publisher, caller_id, topic)
+        .await
+        .map_err(SubscriberError::RequestTopicError)?;
+    if protocol != "TCPROS" {
+        return Err(SubscriberError::ProtocolMismatch(protocol));
+    }
+
     // Connect to the publisher
-    let mut stream = TcpStream::connect(addr).await?;
+    let mut stream = TcpStream::connect((hostname.as_str(), port as u16)).await?;
 
     // Exchange header information to describe what the subscriber will listen to
-    let pub_caller_id = Arc::new(
-        handshake::<T, _>(&mut stream, &caller_id, &topic)
-            .await?
-            .unwrap_or_default(),
-    );
+    let header = Arc::new(handshake(&mut stream, caller_id, topic, description).await?);
+    let publisher = Arc::new(publisher.to_owned());
+    let pub_caller_id = Arc::new(header.get("callerid").cloned().unwrap_or_default());
+    connected.store(true, Ordering::Relaxed);
 
-    async {
+    let closed = async {
         info!("connected");
 
         // Read packets from the stream
         loop {
             match super::read_packet(&mut stream).await {
                 Ok(package) => {
-                    if let Err(mpsc::error::TrySendError::Closed(_)) =
-                        data_tx.try_send(RawMessage {
+                    counters.message(package.len());
+                    if data_tx
+                        .send(Packet {
+                            publisher: publisher.clone(),
                             caller_id: pub_caller_id.clone(),
+                            header: header.clone(),
                             data: package,
+                            counters: counters.clone(),
                         })
+                        .await
+                        .is_err()
                     {
                         // If the channel is closed, break out of the loop, effectively disconnecting
                         info!("subscriber cancelled");
-                        break;
+                        break LinkClosed::Cancelled;
                     }
                 }
                 Err(e) => {
@@ -224,7 +649,7 @@ async fn connect_to_publisher<T: Message>(
                         ErrorKind::UnexpectedEof => info!("socket closed"),
                         _ => error!("disconnecting: {}", e),
                     }
-                    break;
+                    break LinkClosed::Disconnected;
                 }
             }
         }
@@ -235,43 +660,72 @@ async fn connect_to_publisher<T: Message>(
     ))
     .await;
 
-    Ok(())
+    Ok(closed)
+}
+
+/// Asks the publisher at the specified uri for the address at which it publishes the topic
+async fn request_topic(
+    publisher_uri: &str,
+    caller_id: &str,
+    topic: &str,
+) -> Response<(String, String, i32)> {
+    let uri = publisher_uri
+        .parse()
+        .map_err(|_| ResponseError::Client(format!("invalid uri '{}'", publisher_uri)))?;
+    rosxmlrpc::Client::new(uri)
+        .request("requestTopic", &(caller_id, topic, [["TCPROS"]]))
+        .await
 }
 
 /// Performs a handshake after the initial connection has been made to let the publisher know what
-/// we are interested in. Returns the caller_id of the publisher on a successful connection.
-async fn handshake<T: Message, U: AsyncRead + AsyncWrite + Unpin>(
+/// we are interested in. Returns the connection header of the publisher on a successful
+/// connection.
+async fn handshake<U: AsyncRead + AsyncWrite + Unpin>(
     stream: &mut U,
     caller_id: &str,
     topic: &str,
-) -> Result<Option<String>, SubscriberError> {
-    write_handshake_request::<T, U>(stream, caller_id, topic).await?;
-    read_handshake_response::<T, U>(stream).await
+    description: &MessageDescription,
+) -> Result<HashMap<String, String>, SubscriberError> {
+    write_handshake_request(stream, caller_id, topic, description).await?;
+    read_handshake_response(stream, description).await
 }
 
 /// Write the request message to the given stream
-async fn write_handshake_request<T: Message, U: AsyncWrite + Unpin>(
+async fn write_handshake_request<U: AsyncWrite + Unpin>(
     mut stream: &mut U,
     caller_id: &str,
     topic: &str,
+    description: &MessageDescription,
 ) -> Result<(), SubscriberError> {
     let mut fields = HashMap::<String, String>::new();
-    fields.insert(String::from("message_definition"), T::msg_definition());
+    fields.insert(
+        String::from("message_definition"),
+        description.msg_definition.clone(),
+    );
     fields.insert(String::from("callerid"), String::from(caller_id));
     fields.insert(String::from("topic"), String::from(topic));
-    fields.insert(String::from("md5sum"), T::md5sum());
-    fields.insert(String::from("type"), T::msg_type());
+    fields.insert(String::from("md5sum"), description.md5sum.clone());
+    fields.insert(String::from("type"), description.msg_type.clone());
     header::encode_and_write(&mut stream, &fields)
         .await
         .map_err(Into::into)
 }
 
 /// Read the handshake response from the publisher
-async fn read_handshake_response<T: Message, U: AsyncRead + Unpin>(
+async fn read_handshake_response<U: AsyncRead + Unpin>(
     mut stream: &mut U,
-) -> Result<Option<String>, SubscriberError> {
+    description: &MessageDescription,
+) -> Result<HashMap<String, String>, SubscriberError> {
     let fields = header::read_and_decode(&mut stream).await?;
-    header::match_field(&fields, "md5sum", &T::md5sum())?;
-    header::match_field(&fields, "type", &T::msg_type())?;
-    Ok(fields.get("callerid").cloned())
+    if let Some(error) = fields.get("error") {
+        return Err(SubscriberError::Rejected(error.clone()));
+    }
+    // A subscriber that accepts any message does not check what the publisher sends
+    if description.md5sum != "*" {
+        header::match_field(&fields, "md5sum", &description.md5sum)?;
+    }
+    if description.msg_type != "*" {
+        header::match_field(&fields, "type", &description.msg_type)?;
+    }
+    Ok(fields)
 }
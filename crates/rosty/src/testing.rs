@@ -0,0 +1,166 @@
+//! Helpers to test nodes without a ROS installation. Instead of connecting to an external
+//! `roscore`, the node connects to a master that is embedded in the test process and listens on
+//! an ephemeral port. Every test therefore gets its own master and its own `Node`, so tests do not
+//! interfere with each other through global state like `ROS_MASTER_URI` or the default node and
+//! several tests can run in the same test binary.
+
+use crate::node::{Node, NodeArgs, Publisher};
+use crate::rosxmlrpc::{Client, Response};
+use rosty_msg::rosgraph_msgs::Clock;
+use rosty_msg::Time;
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+
+/// The caller id used for calls to the master that are not made by the node
+const CALLER_ID: &str = "/testing";
+
+/// A ROS master that runs in the current process. The master is shut down when it is dropped.
+pub struct TestMaster {
+    uri: String,
+    client: Client,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl TestMaster {
+    /// Starts a new master on an ephemeral port of the local machine. Must be called from within a
+    /// tokio runtime.
+    pub fn start() -> Result<TestMaster, failure::Error> {
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        let (server, addr) =
+            rosty_master::Master::new().bind(&SocketAddr::from(([127, 0, 0, 1], 0)), async {
+                let _ = shutdown_signal.await;
+            })?;
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                error!("embedded master failed: {}", err);
+            }
+        });
+
+        let uri = format!("http://localhost:{}/", addr.port());
+        Ok(TestMaster {
+            client: Client::new(uri.parse()?),
+            uri,
+            _shutdown: shutdown,
+        })
+    }
+
+    /// Returns the URI of the master
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Sets a parameter on the parameter server of the master
+    pub async fn set_param<T: Serialize>(&self, key: &str, value: &T) -> Response<()> {
+        self.client
+            .request("setParam", &(CALLER_ID, key, value))
+            .await
+            // We can ignore the i32, because the ROS standard says it is ignorable
+            .map(|_val: i32| ())
+    }
+}
+
+/// Starts an embedded master, creates a node that is registered with it and runs the future
+/// returned by `generator` for the node to completion.
+pub fn run_with_node<F, R>(generator: F)
+where
+    F: FnOnce(Node) -> R,
+    R: Future<Output = ()>,
+{
+    run(false, |args| args, generator)
+}
+
+/// Same as `run_with_node` but with the `/use_sim_time` parameter set before the node is
+/// created.
+pub fn run_with_node_simtime<F, R>(generator: F)
+where
+    F: FnOnce(Node) -> R,
+    R: Future<Output = ()>,
+{
+    run(true, |args| args, generator)
+}
+
+/// Same as `run_with_node` but the arguments of the node are modified by `configure` before the
+/// node is created.
+pub fn run_with_node_args<F, R>(configure: impl FnOnce(NodeArgs) -> NodeArgs, generator: F)
+where
+    F: FnOnce(Node) -> R,
+    R: Future<Output = ()>,
+{
+    run(false, configure, generator)
+}
+
+fn run<F, R>(use_sim_time: bool, configure: impl FnOnce(NodeArgs) -> NodeArgs, generator: F)
+where
+    F: FnOnce(Node) -> R,
+    R: Future<Output = ()>,
+{
+    let mut runtime = tokio::runtime::Runtime::new().expect("could not create a tokio runtime");
+    runtime.block_on(async move {
+        let master = TestMaster::start().expect("could not start the master");
+
+        // Like `roscore`, identify the run with a parameter
+        master
+            .set_param("/run_id", &run_id())
+            .await
+            .expect("could not set the /run_id parameter");
+        if use_sim_time {
+            master
+                .set_param("/use_sim_time", &true)
+                .await
+                .expect("could not set the /use_sim_time parameter");
+        }
+
+        let args = NodeArgs::new("test")
+            .set_master_uri(master.uri())
+            .set_hostname("localhost");
+        let node = Node::new(configure(args))
+            .await
+            .expect("could not create the node");
+
+        generator(node).await;
+    })
+}
+
+/// Returns an identifier that is unique for this run of the tests
+fn run_id() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}-{}", std::process::id(), since_epoch.as_nanos())
+}
+
+/// Returns the names of all topics that have a publisher or a subscriber, like `rostopic list`
+pub async fn list_topics(node: &Node) -> Response<Vec<String>> {
+    let state = node.system_state().await?;
+    let mut topics: Vec<String> = state
+        .publishers
+        .into_iter()
+        .chain(state.subscribers)
+        .map(|(topic, _)| topic)
+        .collect();
+    topics.sort();
+    topics.dedup();
+    Ok(topics)
+}
+
+/// Returns the names of all services, like `rosservice list`
+pub async fn list_services(node: &Node) -> Response<Vec<String>> {
+    let state = node.system_state().await?;
+    Ok(state
+        .services
+        .into_iter()
+        .map(|(service, _)| service)
+        .collect())
+}
+
+/// Publishes `time` on the `/clock` topic with `node`, like `rostopic pub /clock`. The message is
+/// latched so it is also received by nodes that subscribe later on. The returned publisher must be
+/// kept alive for as long as the clock should be available.
+pub async fn publish_clock(node: &Node, time: Time) -> Result<Publisher<Clock>, failure::Error> {
+    let publisher = node.publish_latched::<Clock>("/clock", 1).await?;
+    publisher.send(Clock { clock: time }).await?;
+    Ok(publisher)
+}
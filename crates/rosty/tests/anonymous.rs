@@ -0,0 +1,30 @@
+use rosty::testing::TestMaster;
+use rosty::{Node, NodeArgs};
+
+pub mod util;
+
+#[test]
+fn anonymous() {
+    util::run_with_node_args(
+        |args| args.set_anonymous(true),
+        |node| async move {
+            let name = node.name();
+            assert!(name.starts_with("/test_"), "unexpected name {}", name);
+            assert_eq!(node.resolve_name(name).unwrap(), name);
+            println!("✓ a unique suffix is appended to the name.");
+
+            // Nodes that are created right after each other still get different names
+            let master = TestMaster::start().unwrap();
+            let args = || {
+                NodeArgs::new("test")
+                    .set_master_uri(master.uri())
+                    .set_hostname("localhost")
+                    .set_anonymous(true)
+            };
+            let first = Node::new(args()).await.unwrap();
+            let second = Node::new(args()).await.unwrap();
+            assert_ne!(first.name(), second.name());
+            println!("✓ anonymous nodes in the same process get different names.");
+        },
+    )
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+use xmlrpc::Value;
+
+pub mod util;
+
+/// Calls a method of the slave API of the given node and returns the value of the response
+async fn call_slave(node: &rosty::Node, method: &str) -> Value {
+    let uri = node.uri().parse().unwrap();
+    let response = xmlrpc::call_with_params(&uri, method, vec![Value::String("/test".into())])
+        .await
+        .unwrap()
+        .unwrap();
+    match response.as_slice() {
+        [Value::Array(items)] => match items.as_slice() {
+            [Value::Int(1), _, value] => value.clone(),
+            _ => panic!("{} failed: {:?}", method, items),
+        },
+        _ => panic!("invalid response: {:?}", response),
+    }
+}
+
+#[test]
+fn bus_info() {
+    util::run_with_node(|node| async move {
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        let _subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
+        );
+        // Give the subscriber time to process the handshake response of the publisher
+        tokio::time::delay_for(Duration::from_millis(200)).await;
+
+        let foo = Value::Array(vec![
+            Value::String("/foo".into()),
+            Value::String("std_msgs/String".into()),
+        ]);
+        assert_eq!(
+            call_slave(&node, "getPublications").await,
+            Value::Array(vec![foo.clone()])
+        );
+        assert_eq!(
+            call_slave(&node, "getSubscriptions").await,
+            Value::Array(vec![foo])
+        );
+        println!("✓ publications and subscriptions are listed.");
+
+        // The node is connected to itself, so both ends of the connection are listed
+        let connections = match call_slave(&node, "getBusInfo").await {
+            Value::Array(connections) => connections,
+            value => panic!("invalid bus info: {:?}", value),
+        };
+        assert_eq!(connections.len(), 2);
+        let mut ids = Vec::new();
+        for connection in connections {
+            let fields = match connection {
+                Value::Array(fields) => fields,
+                value => panic!("invalid connection: {:?}", value),
+            };
+            match &fields[0] {
+                Value::Int(id) => ids.push(*id),
+                value => panic!("invalid connection id: {:?}", value),
+            }
+            let destination = match fields[2] {
+                Value::String(ref direction) if direction == "i" => node.uri(),
+                Value::String(ref direction) if direction == "o" => node.name(),
+                ref value => panic!("invalid direction: {:?}", value),
+            };
+            assert_eq!(
+                fields[1..],
+                [
+                    Value::String(destination.to_owned()),
+                    fields[2].clone(),
+                    Value::String("TCPROS".into()),
+                    Value::String("/foo".into()),
+                    Value::Bool(true),
+                ]
+            );
+        }
+        assert_ne!(ids[0], ids[1]);
+        println!("✓ both ends of the connection are listed.");
+    })
+}
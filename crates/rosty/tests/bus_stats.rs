@@ -0,0 +1,93 @@
+use rosty::Direction;
+use std::time::Duration;
+use xmlrpc::Value;
+
+pub mod util;
+
+#[test]
+fn bus_stats() {
+    util::run_with_node(|node| async move {
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        // The subscriber only queues a single message, it is never read from
+        let subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 1)
+            .await
+            .unwrap();
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
+        );
+
+        for _ in 0..3 {
+            publisher
+                .send(rosty_msg::std_msgs::String {
+                    data: "Hello from Rust".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        tokio::time::delay_for(Duration::from_millis(500)).await;
+
+        // Every message is 4 bytes for the length of the message and 4 bytes for the length of the
+        // string followed by its characters
+        let bytes = 3 * (8 + "Hello from Rust".len() as u64);
+        let publisher_stats = publisher.stats();
+        assert_eq!(publisher_stats.len(), 1);
+        assert_eq!(publisher_stats[0].direction, Direction::Outbound);
+        assert_eq!(publisher_stats[0].stats.messages, 3);
+        assert_eq!(publisher_stats[0].stats.bytes, bytes);
+        assert_eq!(publisher_stats[0].stats.drops, 0);
+
+        let subscriber_stats = subscriber.stats().await;
+        assert_eq!(subscriber_stats.len(), 1);
+        assert_eq!(subscriber_stats[0].direction, Direction::Inbound);
+        assert_eq!(subscriber_stats[0].stats.messages, 3);
+        assert_eq!(subscriber_stats[0].stats.bytes, bytes);
+        assert_eq!(subscriber_stats[0].stats.drops, 2);
+        println!("✓ the publisher and subscriber count the messages.");
+
+        // The same statistics are available through the slave API
+        let uri = node.uri().parse().unwrap();
+        let response =
+            xmlrpc::call_with_params(&uri, "getBusStats", vec![Value::String("/test".into())])
+                .await
+                .unwrap()
+                .unwrap();
+        let bytes = Value::Int(bytes as i32);
+        assert_eq!(
+            response,
+            vec![Value::Array(vec![
+                Value::Int(1),
+                Value::String("Bus stats".into()),
+                Value::Array(vec![
+                    Value::Array(vec![Value::Array(vec![
+                        Value::String("/foo".into()),
+                        bytes.clone(),
+                        Value::Array(vec![Value::Array(vec![
+                            Value::Int(publisher_stats[0].id as i32),
+                            bytes.clone(),
+                            Value::Int(3),
+                            Value::Bool(true),
+                        ])]),
+                    ])]),
+                    Value::Array(vec![Value::Array(vec![
+                        Value::String("/foo".into()),
+                        Value::Array(vec![Value::Array(vec![
+                            Value::Int(subscriber_stats[0].id as i32),
+                            bytes,
+                            Value::Int(3),
+                            Value::Int(2),
+                            Value::Bool(true),
+                        ])]),
+                    ])]),
+                    Value::Array(vec![]),
+                ]),
+            ])]
+        );
+        println!("✓ the statistics are reported by getBusStats.");
+    })
+}
@@ -0,0 +1,79 @@
+use futures::StreamExt;
+use rosty::ClockJump;
+use rosty_msg::rosgraph_msgs::Clock;
+use rosty_msg::Time;
+use std::time::Duration;
+
+pub mod util;
+
+/// Returns the time at `sec` seconds
+fn time(sec: u32) -> Time {
+    Time { sec, nsec: 0 }
+}
+
+/// Returns the next jump of the clock
+async fn next_jump(jumps: &mut rosty::ClockJumps) -> Option<ClockJump> {
+    tokio::time::timeout(Duration::from_secs(10), jumps.next())
+        .await
+        .expect("no clock jump was received")
+}
+
+#[test]
+fn clock_jumps() {
+    util::run_with_node_simtime(|node| async move {
+        let clock = util::publish_clock(&node, time(100)).await.unwrap();
+        node.wait_for_valid_time(Duration::from_secs(10))
+            .await
+            .unwrap();
+        let set_time = |sec| {
+            let clock = &clock;
+            async move {
+                clock.send(Clock { clock: time(sec) }).await.unwrap();
+                tokio::time::delay_for(Duration::from_millis(50)).await;
+            }
+        };
+        let mut jumps = node.clock_jumps(Some(Duration::from_secs(5)));
+
+        set_time(101).await;
+        set_time(110).await;
+        assert_eq!(
+            next_jump(&mut jumps).await,
+            Some(ClockJump {
+                previous: time(101),
+                current: time(110)
+            })
+        );
+        println!("✓ forward jumps beyond the threshold are reported.");
+
+        set_time(50).await;
+        let jump = next_jump(&mut jumps).await.unwrap();
+        assert!(jump.is_backwards());
+        assert_eq!(jump.previous, time(110));
+        assert_eq!(jump.current, time(50));
+        println!("✓ backwards jumps are reported.");
+
+        // A timer restarts when the time jumps backwards
+        let mut timer = node.timer(Duration::from_secs(10));
+        let tick = tokio::spawn(async move { timer.tick().await.unwrap() });
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+        set_time(40).await;
+        set_time(50).await;
+        let event = tokio::time::timeout(Duration::from_secs(10), tick)
+            .await
+            .expect("the timer did not restart")
+            .unwrap();
+        assert_eq!(event.current_expected, time(50));
+        println!("✓ timers restart when the time jumps backwards.");
+
+        // A sleep is interrupted when the time jumps backwards
+        let sleeping_node = node.clone();
+        let sleep = tokio::spawn(async move { sleeping_node.sleep_until(time(60)).await });
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+        set_time(30).await;
+        assert!(!tokio::time::timeout(Duration::from_secs(10), sleep)
+            .await
+            .unwrap()
+            .unwrap());
+        println!("✓ sleeping is interrupted when the time jumps backwards.");
+    })
+}
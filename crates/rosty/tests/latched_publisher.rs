@@ -0,0 +1,30 @@
+use futures::StreamExt;
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn latched_publisher() {
+    util::run_with_node(|node| async move {
+        // Start a latching publisher and send a single message before anyone is subscribed
+        let publisher = node
+            .publish_latched::<rosty_msg::std_msgs::String>("/latched", 8)
+            .await
+            .unwrap();
+        let msg = rosty_msg::std_msgs::String {
+            data: "Hello from the past".to_string(),
+        };
+        publisher.send(msg).await.unwrap();
+
+        // A subscriber that connects later should still receive the message
+        let mut subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/latched", 8)
+            .await
+            .unwrap();
+
+        let (_, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("latched message was never received"),
+            msg = subscriber.next() => msg.unwrap());
+        assert_eq!(msg.data, "Hello from the past");
+    })
+}
@@ -0,0 +1,105 @@
+use rosty::{LoggerLevels, ROOT_LOGGER};
+use rosty_msg::roscpp::{GetLoggers, GetLoggersReq, Logger, SetLoggerLevel, SetLoggerLevelReq};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+pub mod util;
+
+/// Counts the events of the application that pass the filter
+struct CountingLayer(Arc<AtomicUsize>);
+
+impl<S: Subscriber> Layer<S> for CountingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target().starts_with("app::") {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn logger_levels() {
+    util::run_with_node(|node| async move {
+        let count = Arc::new(AtomicUsize::new(0));
+        let (filter, levels) =
+            LoggerLevels::with_targets(LevelFilter::INFO, vec![("app::other", LevelFilter::WARN)]);
+        tracing::subscriber::set_global_default(
+            tracing_subscriber::registry()
+                .with(filter)
+                .with(CountingLayer(count.clone())),
+        )
+        .unwrap();
+        let _services = node.advertise_logger_services(levels).await.unwrap();
+        let log = || {
+            tracing::debug!(target: "app::connection", "connection details");
+            tracing::debug!(target: "app::other", "other details");
+        };
+
+        log();
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        node.service_client::<SetLoggerLevel>("/test/set_logger_level", false)
+            .unwrap()
+            .call(&SetLoggerLevelReq {
+                logger: "app::connection".into(),
+                level: "DEBUG".into(),
+            })
+            .await
+            .unwrap();
+        log();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        println!("✓ the level of a logger is changed.");
+
+        let response = node
+            .service_client::<GetLoggers>("/test/get_loggers", false)
+            .unwrap()
+            .call(&GetLoggersReq {})
+            .await
+            .unwrap();
+        assert_eq!(
+            response.loggers,
+            vec![
+                Logger {
+                    name: ROOT_LOGGER.into(),
+                    level: "info".into(),
+                },
+                Logger {
+                    name: "app::connection".into(),
+                    level: "debug".into(),
+                },
+                Logger {
+                    name: "app::other".into(),
+                    level: "warn".into(),
+                },
+            ]
+        );
+        println!("✓ the loggers are listed.");
+
+        // The root logger changes the level of targets without a level of their own
+        node.service_client::<SetLoggerLevel>("/test/set_logger_level", false)
+            .unwrap()
+            .call(&SetLoggerLevelReq {
+                logger: ROOT_LOGGER.into(),
+                level: "DEBUG".into(),
+            })
+            .await
+            .unwrap();
+        tracing::debug!(target: "app::root", "root details");
+        log();
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        println!("✓ the level of the root logger is changed.");
+
+        assert!(node
+            .service_client::<SetLoggerLevel>("/test/set_logger_level", false)
+            .unwrap()
+            .call(&SetLoggerLevelReq {
+                logger: "app".into(),
+                level: "loud".into(),
+            })
+            .await
+            .is_err());
+        println!("✓ unknown levels are rejected.");
+    })
+}
@@ -0,0 +1,68 @@
+use futures::StreamExt;
+use rosty::testing::TestMaster;
+use rosty::{Node, NodeArgs};
+use std::time::Duration;
+
+/// Returns the arguments of a node that connects to `master`
+fn args(name: &str, master: &TestMaster) -> NodeArgs {
+    NodeArgs::new(name)
+        .set_master_uri(master.uri())
+        .set_hostname("localhost")
+}
+
+#[test]
+fn multiple_nodes() {
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let master = TestMaster::start().unwrap();
+        let talker = Node::new(args("talker", &master)).await.unwrap();
+        let listener = Node::new(args("listener", &master).set_namespace("/ns"))
+            .await
+            .unwrap();
+        assert_eq!(talker.name(), "/talker");
+        assert_eq!(listener.name(), "/ns/listener");
+        assert_ne!(talker.uri(), listener.uri());
+        println!("✓ several nodes run in the same process.");
+
+        let mut subscriber = listener
+            .subscribe::<rosty_msg::std_msgs::String>("/chatter", 1)
+            .await
+            .unwrap();
+        // A clone refers to the same node
+        let publisher = talker
+            .clone()
+            .publish::<rosty_msg::std_msgs::String>("/chatter", 1)
+            .await
+            .unwrap();
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
+        );
+        publisher
+            .send(rosty_msg::std_msgs::String {
+                data: "hello".to_owned(),
+            })
+            .await
+            .unwrap();
+        let (_, message) = tokio::time::timeout(Duration::from_secs(10), subscriber.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.data, "hello");
+        println!("✓ nodes communicate with each other.");
+
+        // A node with the same name connected to another master does not replace the talker
+        let other_master = TestMaster::start().unwrap();
+        let other = Node::new(args("talker", &other_master)).await.unwrap();
+        assert_eq!(other.name(), "/talker");
+        assert!(!talker.is_awaiting_shutdown());
+        println!("✓ nodes connect to different masters.");
+
+        talker.shutdown();
+        assert!(talker.is_awaiting_shutdown());
+        assert!(!listener.is_awaiting_shutdown());
+        assert!(!other.is_awaiting_shutdown());
+        println!("✓ nodes are shut down independently.");
+    })
+}
@@ -0,0 +1,87 @@
+use futures::StreamExt;
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn multiple_subscriptions() {
+    util::run_with_node(|node| async move {
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+
+        // Subscribe to the same topic twice
+        let mut first = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        let mut second = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+
+        // Subscribing with a different type is not allowed
+        assert!(node
+            .subscribe::<rosty_msg::rosgraph_msgs::Log>("/foo", 8)
+            .await
+            .is_err());
+
+        // Send some data
+        tokio::spawn(async move {
+            loop {
+                let msg = rosty_msg::std_msgs::String {
+                    data: "Hello from Rust".to_string(),
+                };
+                publisher.send(msg).await.unwrap();
+                tokio::time::delay_for(Duration::from_millis(10)).await;
+            }
+        });
+
+        // Both subscriptions should receive the messages
+        for subscriber in [&mut first, &mut second].iter_mut() {
+            let (_, msg) = tokio::select!(
+                _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+                msg = subscriber.next() => msg.unwrap());
+            assert_eq!(msg.data, "Hello from Rust");
+        }
+        println!("✓ both subscriptions received a message.");
+
+        // Dropping one of the subscriptions keeps the other one alive
+        drop(first);
+        tokio::time::delay_for(Duration::from_millis(500)).await;
+        assert!(util::list_topics(&node)
+            .await
+            .unwrap()
+            .iter()
+            .any(|t| t == "/foo"));
+        let (_, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+            msg = second.next() => msg.unwrap());
+        assert_eq!(msg.data, "Hello from Rust");
+        println!("✓ remaining subscription still receives messages.");
+
+        // Handles that subscribe at the same time share the subscription
+        let (bar, other_bar) = futures::join!(
+            node.subscribe::<rosty_msg::std_msgs::String>("/bar", 8),
+            node.subscribe::<rosty_msg::std_msgs::String>("/bar", 8)
+        );
+        assert!(bar.is_ok() && other_bar.is_ok());
+        println!("✓ concurrent subscriptions share the subscription.");
+
+        // Cancelling a subscription while it is registered does not block later subscriptions
+        let _ = tokio::time::timeout(
+            Duration::from_micros(1),
+            node.subscribe::<rosty_msg::std_msgs::String>("/baz", 8),
+        )
+        .await;
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            node.subscribe::<rosty_msg::std_msgs::String>("/baz", 8),
+        )
+        .await
+        .expect("subscribing did not finish")
+        .unwrap();
+        println!("✓ cancelled subscriptions are cleaned up.");
+    })
+}
@@ -2,11 +2,11 @@ pub mod util;
 
 #[test]
 fn no_sim_time() {
-    util::run_with_node(async {
+    util::run_with_node(|node| async move {
         // Don't use simtime normally
-        assert!(!rosty::is_using_sim_time());
+        assert!(!node.is_using_sim_time());
 
         // Check if we are getting a time from the now function
-        assert!(rosty::now().seconds() > 0.0,)
+        assert!(node.now().unwrap().seconds() > 0.0,)
     });
 }
@@ -0,0 +1,43 @@
+use futures::StreamExt;
+use rosty::ParamWatch;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub mod util;
+
+/// Returns the next value of the watched parameter
+async fn next(watch: &mut ParamWatch<i32>) -> Option<i32> {
+    tokio::select!(
+        _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no update was received"),
+        value = watch.next() => value.unwrap())
+}
+
+#[test]
+fn param_watch() {
+    util::run_with_node(|node| async move {
+        let param = node.param("/ns/value");
+        let mut watch = param.watch::<i32>().await.unwrap();
+
+        // The parameter does not exist yet
+        assert_eq!(next(&mut watch).await, None);
+        println!("✓ the current value is received first.");
+
+        param.set(&1).await.unwrap();
+        assert_eq!(next(&mut watch).await, Some(1));
+        assert_eq!(param.get::<i32>().await.unwrap(), 1);
+        println!("✓ changes are received.");
+
+        // Changing the namespace of the parameter also changes the parameter
+        let mut namespace = HashMap::new();
+        namespace.insert("value", 2);
+        node.param("/ns").set(&namespace).await.unwrap();
+        assert_eq!(next(&mut watch).await, Some(2));
+        assert_eq!(param.get::<i32>().await.unwrap(), 2);
+        println!("✓ changes of the namespace are received.");
+
+        param.delete().await.unwrap();
+        assert_eq!(next(&mut watch).await, None);
+        assert!(param.get::<i32>().await.is_err());
+        println!("✓ deleting the parameter is received.");
+    })
+}
@@ -2,8 +2,9 @@ pub mod util;
 
 #[test]
 fn test_parameter_api() {
-    util::run_with_node(async {
-        let parameters = rosty::param_names()
+    util::run_with_node(|node| async move {
+        let parameters = node
+            .get_all_param_names()
             .await
             .expect("No parameters could be retrieved from the ROS master");
         assert!(
@@ -11,7 +12,7 @@ fn test_parameter_api() {
             "No parameters were found on the ROS master"
         );
 
-        let param = rosty::param("test");
+        let param = node.param("test");
 
         // It should not exist when starting
         assert_eq!(param.exists().await.unwrap(), false);
@@ -40,7 +41,7 @@ fn test_parameter_api() {
         // And it should no longer exist
         assert_eq!(param.exists().await.unwrap(), false);
 
-        let root_param = rosty::param("/baz");
+        let root_param = node.param("/baz");
 
         // It should not exist when starting
         assert_eq!(root_param.exists().await.unwrap(), false);
@@ -50,6 +51,6 @@ fn test_parameter_api() {
 
         // Now search for this param, and check the value
         assert_eq!(root_param.get::<i32>().await.unwrap(), 20);
-        //assert_eq!(rosty::search_param::<i32, _>("baz").await.unwrap(), 20);
+        //assert_eq!(node.search_param::<i32, _>("baz").await.unwrap(), 20);
     });
 }
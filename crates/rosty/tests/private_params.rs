@@ -0,0 +1,27 @@
+pub mod util;
+
+#[test]
+fn private_params() {
+    util::run_with_node_args(
+        |args| {
+            args.set_namespace("/ns")
+                .add_param("rate", "10")
+                .add_param("gain", "0.5")
+                .add_param("enabled", "true")
+                .add_param("frame", "base_link")
+                .add_param("quoted", "'42'")
+        },
+        |node| async move {
+            assert_eq!(node.param("~rate").get::<i32>().await.unwrap(), 10);
+            assert_eq!(node.param("/ns/test/rate").get::<i32>().await.unwrap(), 10);
+            assert_eq!(node.param("~gain").get::<f64>().await.unwrap(), 0.5);
+            assert!(node.param("~enabled").get::<bool>().await.unwrap());
+            assert_eq!(
+                node.param("~frame").get::<String>().await.unwrap(),
+                "base_link"
+            );
+            assert_eq!(node.param("~quoted").get::<String>().await.unwrap(), "42");
+            println!("✓ private parameters are set with the inferred types.");
+        },
+    )
+}
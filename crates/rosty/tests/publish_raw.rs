@@ -0,0 +1,74 @@
+use futures::StreamExt;
+use rosty::MessageDescription;
+use rosty_msg::{Message, RosMsg};
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn publish_raw() {
+    util::run_with_node(|node| async move {
+        let publisher = node
+            .publish_raw(
+                "/foo",
+                MessageDescription::new(
+                    &rosty_msg::std_msgs::String::msg_type(),
+                    &rosty_msg::std_msgs::String::md5sum(),
+                    &rosty_msg::std_msgs::String::msg_definition(),
+                ),
+                8,
+            )
+            .await
+            .unwrap();
+
+        // Publishing the same topic with a different type is not allowed
+        assert!(node
+            .publish::<rosty_msg::rosgraph_msgs::Log>("/foo", 8)
+            .await
+            .is_err());
+
+        let mut subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
+        );
+
+        // Send a serialized message without its length
+        let data = rosty_msg::std_msgs::String {
+            data: "Hello from Rust".to_string(),
+        }
+        .encode_vec()
+        .unwrap();
+        publisher.send(&data[4..]).await.unwrap();
+
+        let (_, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+            msg = subscriber.next() => msg.unwrap());
+        assert_eq!(msg.data, "Hello from Rust");
+        println!("✓ raw message received by a typed subscriber.");
+
+        // A latched raw publisher sends its last message to subscribers that connect later on
+        let latched = node
+            .publish_raw_latched(
+                "/bar",
+                MessageDescription::of::<rosty_msg::std_msgs::String>(),
+                8,
+            )
+            .await
+            .unwrap();
+        latched.send(&data[4..]).await.unwrap();
+        let mut subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/bar", 8)
+            .await
+            .unwrap();
+        let (_, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+            msg = subscriber.next() => msg.unwrap());
+        assert_eq!(msg.data, "Hello from Rust");
+        println!("✓ latched raw message received by a late subscriber.");
+    })
+}
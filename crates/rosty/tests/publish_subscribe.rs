@@ -5,14 +5,16 @@ pub mod util;
 
 #[test]
 fn publish_subscribe() {
-    util::run_with_node(async {
+    util::run_with_node(|node| async move {
         // Start a publisher
-        let publisher = rosty::publish::<rosty_msg::std_msgs::String>("/foo", 8)
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
             .await
             .unwrap();
 
         // Subscribe to that publisher
-        let mut subscriber = rosty::subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+        let mut subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
             .await
             .unwrap();
 
@@ -0,0 +1,86 @@
+use futures::StreamExt;
+use rosty::SubscriberEvent;
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn publisher_links() {
+    util::run_with_node(|node| async move {
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        let mut disconnects = Box::pin(publisher.subscriber_events().filter(|event| {
+            futures::future::ready(matches!(event, SubscriberEvent::Disconnected(_)))
+        }));
+
+        let mut subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
+        );
+
+        // Dropping the subscriber closes its connection to the publisher
+        drop(subscriber);
+        let event = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no disconnect event was received"),
+            event = disconnects.next() => event.unwrap());
+        assert_eq!(event, SubscriberEvent::Disconnected(node.name().to_owned()));
+        assert_eq!(publisher.num_subscribers(), 0);
+        println!("✓ unsubscribing closes the connection.");
+
+        // Replace the publisher, the subscriber should connect to the new one
+        subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        drop(disconnects);
+        drop(publisher);
+        tokio::time::delay_for(Duration::from_millis(500)).await;
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
+        );
+        publisher
+            .send(rosty_msg::std_msgs::String {
+                data: "Hello from Rust".to_string(),
+            })
+            .await
+            .unwrap();
+        let (_, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+            msg = subscriber.next() => msg.unwrap());
+        assert_eq!(msg.data, "Hello from Rust");
+        println!("✓ the subscriber connects to a new publisher.");
+
+        // A subscriber of another message type is rejected and does not try again
+        let other_publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/bar", 8)
+            .await
+            .unwrap();
+        let mismatched = node
+            .subscribe::<rosty_msg::std_msgs::Header>("/bar", 8)
+            .await
+            .unwrap();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        while !mismatched.stats().await.is_empty() {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "the subscriber kept its link to a mismatched publisher"
+            );
+            tokio::time::delay_for(Duration::from_millis(50)).await;
+        }
+        assert_eq!(other_publisher.num_subscribers(), 0);
+        println!("✓ the subscriber gives up on a publisher of another type.");
+    })
+}
@@ -4,8 +4,14 @@ pub mod util;
 
 #[test]
 fn publisher_register_unregister() {
-    util::run_with_node(async {
-        let has_topic_foo = || async { util::list_topics().unwrap().iter().any(|t| t == "/foo") };
+    util::run_with_node(|node| async move {
+        let has_topic_foo = || async {
+            util::list_topics(&node)
+                .await
+                .unwrap()
+                .iter()
+                .any(|t| t == "/foo")
+        };
 
         let wait_for_topic_foo = |is_available: bool| async move {
             loop {
@@ -20,7 +26,8 @@ fn publisher_register_unregister() {
         assert_eq!(has_topic_foo().await, false);
         println!("✓ /foo is initially not available.");
 
-        let publisher = rosty::publish::<rosty_msg::std_msgs::String>("/foo", 8)
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
             .await
             .unwrap();
 
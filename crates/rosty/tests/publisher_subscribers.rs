@@ -0,0 +1,55 @@
+use futures::StreamExt;
+use rosty::SubscriberEvent;
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn publisher_subscribers() {
+    util::run_with_node(|node| async move {
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        let mut events = Box::pin(publisher.subscriber_events());
+
+        // Initially nobody is listening
+        assert_eq!(publisher.num_subscribers(), 0);
+        assert!(
+            !publisher
+                .wait_for_subscribers(1, Duration::from_millis(100))
+                .await
+        );
+        println!("✓ /foo initially has no subscribers.");
+
+        let subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+
+        // The subscriber should connect to the publisher
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
+        );
+        assert_eq!(publisher.num_subscribers(), 1);
+        println!("✓ /foo has a subscriber.");
+
+        // The connection should have been reported with the caller id of the subscriber
+        let event = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no connect event was received"),
+            event = events.next() => event.unwrap());
+        assert_eq!(event, SubscriberEvent::Connected(node.name().to_owned()));
+        println!("✓ connect event received.");
+
+        // Without publishing anything the publisher should notice that the subscriber is gone
+        drop(subscriber);
+        let event = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no disconnect event was received"),
+            event = events.next() => event.unwrap());
+        assert_eq!(event, SubscriberEvent::Disconnected(node.name().to_owned()));
+        assert_eq!(publisher.num_subscribers(), 0);
+        println!("✓ disconnect event received.");
+    })
+}
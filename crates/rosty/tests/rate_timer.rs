@@ -0,0 +1,101 @@
+use rosty_msg::Time;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+
+pub mod util;
+
+/// Returns the time in nanoseconds
+fn nanos(sec: u32, msec: u32) -> i64 {
+    Time {
+        sec,
+        nsec: msec * 1_000_000,
+    }
+    .nanos()
+}
+
+#[test]
+fn rate_timer() {
+    util::run_with_node_simtime(|node| async move {
+        let clock = util::publish_clock(&node, Time { sec: 100, nsec: 0 })
+            .await
+            .unwrap();
+
+        // Advance the simulated time by 10ms every millisecond
+        let (stop, mut stopped) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let mut time = Time { sec: 100, nsec: 0 }.nanos();
+            loop {
+                tokio::select! {
+                    _ = &mut stopped => return,
+                    _ = tokio::time::delay_for(Duration::from_millis(1)) => {}
+                }
+                time += 10_000_000;
+                clock
+                    .send(rosty_msg::rosgraph_msgs::Clock {
+                        clock: Time::from_nanos(time),
+                    })
+                    .await
+                    .unwrap();
+            }
+        });
+
+        node.wait_for_valid_time(Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        let mut rate = node.rate(10.0);
+        let start = tokio::time::Instant::now();
+        let first = node.now().unwrap();
+        for _ in 0..5 {
+            assert_eq!(rate.sleep().await, 0);
+        }
+        let elapsed = node.now().unwrap() - first;
+        assert!(elapsed.nanos() >= nanos(0, 500), "{:?}", elapsed);
+        // The simulated time runs ten times faster than the wall time
+        assert!(start.elapsed() < Duration::from_millis(400));
+        println!("✓ the rate follows the simulated time.");
+
+        // The loop took longer than three periods
+        let late = node.now().unwrap() + rosty_msg::Duration::from_nanos(nanos(0, 350));
+        while node.now().unwrap() < late {
+            tokio::time::delay_for(Duration::from_millis(1)).await;
+        }
+        assert!(rate.sleep().await >= 3);
+        println!("✓ missed cycles are reported.");
+
+        let mut timer = node.timer(Duration::from_millis(100));
+        let first = timer.tick().await.unwrap();
+        assert_eq!(first.last_expected, None);
+        assert!(first.current_real >= first.current_expected);
+        let second = timer.tick().await.unwrap();
+        assert_eq!(second.last_expected, Some(first.current_expected));
+        assert_eq!(second.last_real, Some(first.current_real));
+        assert_eq!(
+            (second.current_expected - first.current_expected).nanos(),
+            nanos(0, 100)
+        );
+        println!("✓ a periodic timer expires every period.");
+
+        let mut oneshot = node.oneshot_timer(Duration::from_millis(200));
+        let event = oneshot.tick().await.unwrap();
+        assert!(event.current_real >= event.current_expected);
+        assert_eq!(oneshot.tick().await, None);
+        println!("✓ a one-shot timer expires once.");
+
+        // The timer pauses while the simulated time does not advance
+        let _ = stop.send(());
+        let mut timer = node.timer(Duration::from_millis(100));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(300), timer.tick())
+                .await
+                .is_err()
+        );
+        println!("✓ the timer pauses with the simulated time.");
+
+        for frequency in &[0.0, -10.0, f64::NAN, f64::INFINITY] {
+            let rate = std::panic::catch_unwind(AssertUnwindSafe(|| node.rate(*frequency)));
+            assert!(rate.is_err(), "a rate of {} was accepted", frequency);
+        }
+        println!("✓ invalid frequencies are rejected.");
+    })
+}
@@ -0,0 +1,87 @@
+use rosty_msg::roscpp_tutorials::{TwoInts, TwoIntsReq, TwoIntsRes};
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn remapping() {
+    util::run_with_node_args(
+        |args| {
+            args.set_namespace("/ns")
+                .add_remapping("chatter", "/remapped")
+                .add_remapping("~private", "other")
+                .add_remapping("add_two_ints", "/remapped_service")
+        },
+        |node| async move {
+            assert_eq!(node.name(), "/ns/test");
+            assert_eq!(node.resolve_name("/global").unwrap(), "/global");
+            assert_eq!(node.resolve_name("relative").unwrap(), "/ns/relative");
+            assert_eq!(
+                node.resolve_name("~private/x").unwrap(),
+                "/ns/test/private/x"
+            );
+            assert_eq!(node.resolve_name("").unwrap(), "/ns");
+            assert!(node.resolve_name("1invalid").is_err());
+            assert!(node.resolve_name("in valid").is_err());
+            println!("✓ names are resolved relative to the node.");
+
+            assert_eq!(node.resolve_name("chatter").unwrap(), "/remapped");
+            assert_eq!(node.resolve_name("/ns/chatter").unwrap(), "/remapped");
+            assert_eq!(node.resolve_name("~private").unwrap(), "/ns/other");
+            println!("✓ remappings are applied.");
+
+            let _publisher = node
+                .publish::<rosty_msg::std_msgs::String>("chatter", 1)
+                .await
+                .unwrap();
+            let topics = util::list_topics(&node).await.unwrap();
+            assert!(topics.contains(&"/remapped".to_owned()));
+            assert!(!topics.contains(&"/ns/chatter".to_owned()));
+            println!("✓ topics are remapped.");
+
+            let _service = node
+                .advertise_service::<TwoInts, _, _>("add_two_ints", |req| async move {
+                    Ok(TwoIntsRes { sum: req.a + req.b })
+                })
+                .await
+                .unwrap();
+            let services = util::list_services(&node).await.unwrap();
+            assert!(services.contains(&"/remapped_service".to_owned()));
+            assert!(!services.contains(&"/ns/add_two_ints".to_owned()));
+            node.wait_for_service("add_two_ints", Duration::from_secs(10))
+                .await
+                .unwrap();
+            let response = node
+                .service_client::<TwoInts>("/ns/add_two_ints", false)
+                .unwrap()
+                .call(&TwoIntsReq { a: 1, b: 2 })
+                .await
+                .unwrap();
+            assert_eq!(response.sum, 3);
+            println!("✓ services are remapped.");
+
+            node.param("~x").set(&42).await.unwrap();
+            assert_eq!(node.param("/ns/test/x").get::<i32>().await.unwrap(), 42);
+            assert_eq!(node.param("~x").name().unwrap(), "/ns/test/x");
+            println!("✓ private parameters are resolved in the namespace of the node.");
+
+            assert!(node.param("in valid").get::<i32>().await.is_err());
+            assert!(node
+                .subscribe::<rosty_msg::std_msgs::String>("in valid", 1)
+                .await
+                .is_err());
+            assert!(node
+                .advertise_service::<TwoInts, _, _>("in valid", |req| async move {
+                    Ok(TwoIntsRes { sum: req.a + req.b })
+                })
+                .await
+                .is_err());
+            assert!(node.service_client::<TwoInts>("in valid", false).is_err());
+            assert!(node
+                .wait_for_service("in valid", Duration::from_secs(1))
+                .await
+                .is_err());
+            println!("✓ invalid names are rejected.");
+        },
+    )
+}
@@ -0,0 +1,75 @@
+use futures::StreamExt;
+use rosty::RosoutLayer;
+use rosty_msg::rosgraph_msgs::Log;
+use std::time::Duration;
+use tracing::Dispatch;
+use tracing_subscriber::layer::SubscriberExt;
+
+pub mod util;
+
+/// Returns the next message on `/rosout` that is received within `timeout`
+async fn next<S, C>(subscriber: &mut S, timeout: Duration) -> Option<Log>
+where
+    S: futures::Stream<Item = (C, Log)> + Unpin,
+{
+    tokio::time::timeout(timeout, subscriber.next())
+        .await
+        .ok()
+        .map(|message| message.unwrap().1)
+}
+
+#[test]
+fn rosout() {
+    util::run_with_node(|node| async move {
+        let layer = RosoutLayer::new(&node, 16).await.unwrap();
+        let dispatch = Dispatch::new(tracing_subscriber::registry().with(layer));
+        let mut subscriber = node.subscribe::<Log>("/rosout", 16).await.unwrap();
+
+        // Log until the subscriber is connected
+        let mut connected = false;
+        for _ in 0..100 {
+            tracing::dispatcher::with_default(&dispatch, || tracing::info!("connecting"));
+            if next(&mut subscriber, Duration::from_millis(100))
+                .await
+                .is_some()
+            {
+                connected = true;
+                break;
+            }
+        }
+        assert!(connected, "no log message was received");
+        while next(&mut subscriber, Duration::from_millis(100))
+            .await
+            .is_some()
+        {}
+
+        let before = node.now().unwrap();
+        tracing::dispatcher::with_default(&dispatch, || {
+            tracing::warn!(target: "rosty::tcpros::publisher", "this is not published either");
+            tracing::debug!("this is not published");
+            tracing::info!(count = 3, "hello from rust");
+            tracing::warn!("something is off");
+        });
+
+        let log = next(&mut subscriber, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(log.level, Log::INFO);
+        assert_eq!(log.name, "/test");
+        assert_eq!(log.msg, "hello from rust count=3");
+        assert!(log.file.ends_with("rosout.rs"));
+        assert_eq!(log.function, "rosout");
+        assert!(log.line > 0);
+        assert!(log.topics.contains(&"/rosout".to_owned()));
+        assert!(log.header.stamp >= before);
+        assert!(log.header.stamp <= node.now().unwrap());
+        println!("✓ events are published on /rosout.");
+
+        let log = next(&mut subscriber, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(log.level, Log::WARN);
+        assert_eq!(log.msg, "something is off");
+        println!("✓ debug and transport events are not published.");
+    })
+}
@@ -0,0 +1,92 @@
+use rosty::ServiceCallError;
+use rosty_msg::roscpp::{GetLoggers, GetLoggersReq};
+use rosty_msg::roscpp_tutorials::{TwoInts, TwoIntsReq, TwoIntsRes};
+use rosty_msg::Message;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod util;
+
+/// Advertises a service that adds two numbers and counts its calls in `calls`
+async fn advertise_add_two_ints(node: &rosty::Node, calls: Arc<AtomicUsize>) -> impl Drop {
+    node.advertise_service::<TwoInts, _, _>("/add_two_ints", move |req| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        async move {
+            if req.a < 0 || req.b < 0 {
+                Err("only positive numbers are supported".to_owned())
+            } else {
+                Ok(TwoIntsRes { sum: req.a + req.b })
+            }
+        }
+    })
+    .await
+    .unwrap()
+}
+
+#[test]
+fn service_call() {
+    util::run_with_node(|node| async move {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = advertise_add_two_ints(&node, calls.clone()).await;
+
+        // A regular call returns the response of the server
+        let client = node
+            .service_client::<TwoInts>("/add_two_ints", false)
+            .unwrap();
+        let response = client.call(&TwoIntsReq { a: 1, b: 2 }).await.unwrap();
+        assert_eq!(response.sum, 3);
+        println!("✓ service call succeeded.");
+
+        // Errors of the handler are returned to the caller
+        match client.call(&TwoIntsReq { a: -1, b: 2 }).await {
+            Err(ServiceCallError::ServiceFailure(message)) => {
+                assert_eq!(message, "only positive numbers are supported")
+            }
+            result => panic!("expected a service failure, got {:?}", result),
+        }
+        println!("✓ service failure is reported.");
+
+        // A client of another service type is told why the handshake failed
+        match node
+            .service_client::<GetLoggers>("/add_two_ints", false)
+            .unwrap()
+            .call(&GetLoggersReq {})
+            .await
+        {
+            Err(ServiceCallError::ServiceFailure(message)) => {
+                assert!(message.contains(&GetLoggers::md5sum()), "{}", message)
+            }
+            result => panic!("expected a handshake error, got {:?}", result),
+        }
+        println!("✓ handshake errors are reported to the client.");
+
+        // A persistent client can make multiple calls over the same connection
+        let persistent_client = node
+            .service_client::<TwoInts>("/add_two_ints", true)
+            .unwrap();
+        for i in 0..10 {
+            let response = persistent_client
+                .call(&TwoIntsReq { a: i, b: i })
+                .await
+                .unwrap();
+            assert_eq!(response.sum, 2 * i);
+        }
+        println!("✓ persistent service calls succeeded.");
+
+        // When the server goes away and comes back the persistent client reconnects
+        drop(service);
+        tokio::time::delay_for(Duration::from_millis(500)).await;
+        let new_calls = Arc::new(AtomicUsize::new(0));
+        let _service = advertise_add_two_ints(&node, new_calls.clone()).await;
+        let old_count = calls.load(Ordering::SeqCst);
+        let response = persistent_client
+            .call(&TwoIntsReq { a: 4, b: 5 })
+            .await
+            .unwrap();
+        assert_eq!(response.sum, 9);
+        assert_eq!(new_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), old_count);
+        println!("✓ persistent client reconnected.");
+    });
+}
@@ -0,0 +1,62 @@
+use rosty_msg::roscpp_tutorials::{TwoInts, TwoIntsRes};
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn service_register_unregister() {
+    util::run_with_node(|node| async move {
+        let has_service_add = || async {
+            util::list_services(&node)
+                .await
+                .unwrap()
+                .iter()
+                .any(|t| t == "/add_two_ints")
+        };
+
+        let wait_for_service_add = |is_available: bool| async move {
+            loop {
+                if has_service_add().await == is_available {
+                    return;
+                }
+                tokio::time::delay_for(Duration::from_millis(100)).await;
+            }
+        };
+
+        // Initially the service should not be available
+        assert!(!has_service_add().await);
+        println!("✓ /add_two_ints is initially not available.");
+
+        let service = node
+            .advertise_service::<TwoInts, _, _>("/add_two_ints", |req| async move {
+                Ok(TwoIntsRes { sum: req.a + req.b })
+            })
+            .await
+            .unwrap();
+
+        // Now the service should be registered on the master
+        tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("service /add_two_ints was never registered"),
+            _ = wait_for_service_add(true) => {});
+
+        println!("✓ /add_two_ints is now available.");
+
+        // Advertising the same service twice is not allowed
+        assert!(node
+            .advertise_service::<TwoInts, _, _>("/add_two_ints", |_| async move {
+                Err("duplicate".to_owned())
+            })
+            .await
+            .is_err());
+
+        // Drop the service
+        drop(service);
+
+        // Now the service should go away
+        tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(30)) => panic!("service /add_two_ints was never unregistered"),
+            _ = wait_for_service_add(false) => {});
+
+        println!("✓ /add_two_ints is no longer available.");
+    });
+}
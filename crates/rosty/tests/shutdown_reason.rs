@@ -0,0 +1,44 @@
+use rosty::ShutdownReason;
+use xmlrpc::Value;
+
+pub mod util;
+
+#[test]
+fn shutdown_reason() {
+    util::run_with_node(|node| async move {
+        assert_eq!(node.shutdown_reason(), None);
+
+        // The master calls the slave API when another node registers with the same name
+        let uri = node.uri().parse().unwrap();
+        xmlrpc::call_with_params(
+            &uri,
+            "shutdown",
+            vec![
+                Value::String("/master".into()),
+                Value::String("new node registered with same name".into()),
+            ],
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert!(node.is_awaiting_shutdown());
+        assert_eq!(
+            node.shutdown_reason(),
+            Some(ShutdownReason::Remote(
+                "new node registered with same name".into()
+            ))
+        );
+        println!("✓ the reason of a remote shutdown is reported.");
+
+        // Only the first reason is kept
+        node.shutdown();
+        assert_eq!(
+            node.shutdown_reason(),
+            Some(ShutdownReason::Remote(
+                "new node registered with same name".into()
+            ))
+        );
+        println!("✓ the first reason is kept.");
+    })
+}
@@ -3,9 +3,9 @@ use futures::{future::maybe_done, pin_mut};
 
 #[test]
 fn shutdown_token() {
-    util::run_with_node(async {
+    util::run_with_node(|node| async move {
         // Get the future to run the node and wrap it so we can check its output
-        let run_future = maybe_done(rosty::run());
+        let run_future = maybe_done(node.run());
 
         // Pin the future to the stack
         pin_mut!(run_future);
@@ -17,7 +17,7 @@ fn shutdown_token() {
         assert_eq!(run_future.as_mut().output_mut(), None);
 
         // Now trigger the node to shut down
-        rosty::shutdown();
+        node.shutdown();
 
         // Wait for the future to finish (which should be timely)
         run_future.as_mut().await;
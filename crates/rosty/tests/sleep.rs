@@ -0,0 +1,50 @@
+use rosty_msg::Time;
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn sleep() {
+    util::run_with_node_simtime(|node| async move {
+        // No clock message has been received yet
+        assert_eq!(node.now(), None);
+        assert_eq!(
+            node.wait_for_valid_time(Duration::from_millis(100)).await,
+            None
+        );
+        println!("✓ the time is not valid without a clock message.");
+
+        let clock = util::publish_clock(&node, Time { sec: 100, nsec: 0 })
+            .await
+            .unwrap();
+        let now = node
+            .wait_for_valid_time(Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(now, Time { sec: 100, nsec: 0 });
+        assert_eq!(node.now(), Some(now));
+        println!("✓ the time is valid after the first clock message.");
+
+        // Advance the simulated time by a second every 10 milliseconds
+        tokio::spawn(async move {
+            for sec in 101..200 {
+                tokio::time::delay_for(Duration::from_millis(10)).await;
+                clock
+                    .send(rosty_msg::rosgraph_msgs::Clock {
+                        clock: Time { sec, nsec: 0 },
+                    })
+                    .await
+                    .unwrap();
+            }
+        });
+
+        node.sleep_until(Time { sec: 105, nsec: 0 }).await;
+        assert!(node.now().unwrap() >= Time { sec: 105, nsec: 0 });
+        println!("✓ sleep_until waits for the simulated time.");
+
+        let start = node.now().unwrap();
+        node.sleep(Duration::from_secs(3)).await;
+        assert!((node.now().unwrap() - start).seconds() >= 3.0);
+        println!("✓ sleep waits for the simulated time.");
+    })
+}
@@ -1,33 +1,41 @@
 pub mod util;
 use futures::StreamExt;
-use tokio::process::Command;
+use std::time::Duration;
 
 #[test]
 fn subscribe() {
-    util::run_with_node(async {
+    util::run_with_node(|node| async move {
         let test_string = "hello, world!";
 
+        let shutdown_node = node.clone();
         tokio::spawn(
-            rosty::subscribe::<rosty_msg::std_msgs::String>("/test_subscriber", 1)
+            node.subscribe::<rosty_msg::std_msgs::String>("/test_subscriber", 1)
                 .await
                 .unwrap()
-                .for_each(move |(_, message)| async move {
-                    if &message.data == test_string {
-                        rosty::shutdown()
+                .for_each(move |(_, message)| {
+                    if message.data == test_string {
+                        shutdown_node.shutdown()
                     }
+                    async {}
                 }),
         );
 
-        tokio::spawn(
-            Command::new("rostopic")
-                .arg("pub")
-                .arg("/test_subscriber")
-                .arg("std_msgs/String")
-                .arg(test_string)
-                .spawn()
-                .unwrap(),
+        let publisher = node
+            .publish::<rosty_msg::std_msgs::String>("/test_subscriber", 1)
+            .await
+            .unwrap();
+        assert!(
+            publisher
+                .wait_for_subscribers(1, Duration::from_secs(10))
+                .await
         );
+        publisher
+            .send(rosty_msg::std_msgs::String {
+                data: test_string.to_owned(),
+            })
+            .await
+            .unwrap();
 
-        rosty::run().await
+        node.run().await
     })
 }
@@ -0,0 +1,87 @@
+use futures::StreamExt;
+use rosty_msg::{Message, RosMsg};
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn subscribe_raw() {
+    util::run_with_node(|node| async move {
+        let publisher = node
+            .publish_latched::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        publisher
+            .send(rosty_msg::std_msgs::String {
+                data: "Hello from Rust".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let mut subscriber = node.subscribe_raw("/foo", 8).await.unwrap();
+        let (caller_id, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+            msg = subscriber.next() => msg.unwrap());
+        assert_eq!(caller_id, node.name());
+        println!("✓ raw message received.");
+
+        // The connection header describes the message
+        assert_eq!(
+            msg.header.get("type").unwrap(),
+            &rosty_msg::std_msgs::String::msg_type()
+        );
+        assert_eq!(
+            msg.header.get("md5sum").unwrap(),
+            &rosty_msg::std_msgs::String::md5sum()
+        );
+        assert_eq!(
+            msg.header.get("message_definition").unwrap(),
+            &rosty_msg::std_msgs::String::msg_definition()
+        );
+        assert_eq!(msg.header.get("latching").unwrap(), "1");
+        println!("✓ connection header received.");
+
+        // The data is the serialized message
+        let decoded = rosty_msg::std_msgs::String::decode(std::io::Cursor::new(&msg.data)).unwrap();
+        assert_eq!(decoded.data, "Hello from Rust");
+        println!("✓ raw message decoded.");
+
+        // A typed subscription shares the raw subscription and receives the latched message
+        let mut typed = node
+            .subscribe::<rosty_msg::std_msgs::String>("/foo", 8)
+            .await
+            .unwrap();
+        let (_, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+            msg = typed.next() => msg.unwrap());
+        assert_eq!(msg.data, "Hello from Rust");
+        assert!(node
+            .subscribe::<rosty_msg::rosgraph_msgs::Log>("/foo", 8)
+            .await
+            .is_err());
+        println!("✓ typed subscription shares the raw subscription.");
+
+        // Another raw subscription joins the typed subscription and all of them receive new messages
+        let mut second_raw = node.subscribe_raw("/foo", 8).await.unwrap();
+        let _ = second_raw.next().await;
+        publisher
+            .send(rosty_msg::std_msgs::String {
+                data: "Hello again".to_string(),
+            })
+            .await
+            .unwrap();
+        for raw in [&mut subscriber, &mut second_raw].iter_mut() {
+            let (_, msg) = tokio::select!(
+                _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+                msg = raw.next() => msg.unwrap());
+            let decoded =
+                rosty_msg::std_msgs::String::decode(std::io::Cursor::new(&msg.data)).unwrap();
+            assert_eq!(decoded.data, "Hello again");
+        }
+        let (_, msg) = tokio::select!(
+            _ = tokio::time::delay_for(Duration::from_secs(10)) => panic!("no message was received"),
+            msg = typed.next() => msg.unwrap());
+        assert_eq!(msg.data, "Hello again");
+        println!("✓ raw and typed subscriptions receive the same messages.");
+    })
+}
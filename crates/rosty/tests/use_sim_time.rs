@@ -1,11 +1,12 @@
 pub mod util;
+use rosty_msg::Time;
 use tokio::time;
 
 #[test]
 fn use_sim_time() {
-    util::run_with_node_simtime(async {
+    util::run_with_node_simtime(|node| async move {
         // Check if the parameter is set and equals true
-        let param = rosty::param("/use_sim_time");
+        let param = node.param("/use_sim_time");
         assert!(
             param.exists().await.unwrap(),
             "/use_sim_time does not exist"
@@ -16,16 +17,23 @@ fn use_sim_time() {
         );
 
         // In that case is_using_sim_time should also be true
-        assert!(rosty::is_using_sim_time());
+        assert!(node.is_using_sim_time());
 
-        let mut child = util::publish_clock().unwrap();
+        let _clock = util::publish_clock(
+            &node,
+            Time {
+                sec: 100,
+                nsec: 1000,
+            },
+        )
+        .await
+        .unwrap();
         // Wait for the message to be published
         tokio::time::delay_for(time::Duration::from_millis(500)).await;
 
         // Check if we get the value published
-        let duration = rosty::now();
+        let duration = node.now().unwrap();
         assert_eq!(duration.sec, 100);
         assert_eq!(duration.nsec, 1000);
-        child.kill().expect("Could not stop clock publisher");
     })
 }
@@ -0,0 +1,77 @@
+use futures::StreamExt;
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn wait_for_message() {
+    util::run_with_node(|node| async move {
+        let is_subscribed = || async {
+            node.system_state()
+                .await
+                .unwrap()
+                .subscribers
+                .iter()
+                .any(|(topic, _)| topic == "/wait_for_me")
+        };
+
+        // Nothing is published on the topic so the wait times out
+        let msg = node
+            .wait_for_message::<rosty_msg::std_msgs::String>(
+                "/wait_for_me",
+                Duration::from_millis(100),
+            )
+            .await
+            .unwrap();
+        assert_eq!(msg, None);
+        assert!(!is_subscribed().await);
+        println!("✓ waiting for a message times out.");
+
+        // A latched message is received even though it was sent before subscribing
+        let publisher = node
+            .publish_latched::<rosty_msg::std_msgs::String>("/wait_for_me", 8)
+            .await
+            .unwrap();
+        publisher
+            .send(rosty_msg::std_msgs::String {
+                data: "Hello once".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let msg = node
+            .wait_for_message::<rosty_msg::std_msgs::String>(
+                "/wait_for_me",
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(msg.map(|msg| msg.data), Some("Hello once".to_string()));
+        println!("✓ the latched message is received.");
+
+        // The subscription is unregistered before returning
+        assert!(!is_subscribed().await);
+        println!("✓ the subscription is unregistered.");
+
+        // The latched message is also received when the topic is already subscribed to
+        let mut subscriber = node
+            .subscribe::<rosty_msg::std_msgs::String>("/wait_for_me", 8)
+            .await
+            .unwrap();
+        let (_, msg) = tokio::time::timeout(Duration::from_secs(10), subscriber.next())
+            .await
+            .expect("latched message was never received")
+            .unwrap();
+        assert_eq!(msg.data, "Hello once");
+        let msg = node
+            .wait_for_message::<rosty_msg::std_msgs::String>(
+                "/wait_for_me",
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(msg.map(|msg| msg.data), Some("Hello once".to_string()));
+        assert!(is_subscribed().await);
+        println!("✓ the latched message is received by a shared subscription.");
+    })
+}
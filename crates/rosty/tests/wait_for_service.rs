@@ -0,0 +1,37 @@
+use rosty::ServiceCallError;
+use rosty_msg::roscpp_tutorials::{TwoInts, TwoIntsRes};
+use std::time::Duration;
+
+pub mod util;
+
+#[test]
+fn wait_for_service() {
+    util::run_with_node(|node| async move {
+        // Waiting for a service that does not exist times out
+        match node
+            .wait_for_service("/add_two_ints", Duration::from_millis(500))
+            .await
+        {
+            Err(ServiceCallError::Timeout) => {}
+            result => panic!("expected a timeout, got {:?}", result),
+        }
+        println!("✓ waiting for a missing service times out.");
+
+        // Advertise the service after a while
+        let advertise = async {
+            tokio::time::delay_for(Duration::from_secs(1)).await;
+            node.advertise_service::<TwoInts, _, _>("/add_two_ints", |req| async move {
+                Ok(TwoIntsRes { sum: req.a + req.b })
+            })
+            .await
+            .unwrap()
+        };
+
+        let (_service, result) = futures::join!(
+            advertise,
+            node.wait_for_service("/add_two_ints", Duration::from_secs(10))
+        );
+        result.unwrap();
+        println!("✓ service became available.");
+    });
+}
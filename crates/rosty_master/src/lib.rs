@@ -0,0 +1,14 @@
+//! A ROS master that implements the Master and Parameter Server APIs. The master can be run as a
+//! standalone binary or embedded in a process, for instance to run tests without a ROS install.
+
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate tracing;
+
+mod master;
+mod param_server;
+mod registrations;
+mod response;
+
+pub use master::Master;
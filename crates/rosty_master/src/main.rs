@@ -0,0 +1,34 @@
+use rosty_master::Master;
+use std::net::SocketAddr;
+use tracing::Level;
+use tracing_subscriber::FmtSubscriber;
+
+const DEFAULT_PORT: u16 = 11311;
+
+#[tokio::main]
+async fn main() -> Result<(), failure::Error> {
+    // Setup logging to the console
+    let subscriber = FmtSubscriber::builder()
+        .with_max_level(Level::INFO)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // The port can be specified with `-p <port>`
+    let mut args = std::env::args().skip(1);
+    let mut port = DEFAULT_PORT;
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("-p", Some(value)) | ("--port", Some(value)) => port = value.parse()?,
+            _ => failure::bail!("usage: rosty_master [-p <port>]"),
+        }
+    }
+
+    // Run the master until ctrl-c is pressed
+    let shutdown_signal = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    let (server, addr) =
+        Master::new().bind(&SocketAddr::from(([0, 0, 0, 0], port)), shutdown_signal)?;
+    tracing::info!("master listening on {}", addr);
+    server.await
+}
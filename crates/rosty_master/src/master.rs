@@ -0,0 +1,594 @@
+use crate::param_server::{ParamServer, ParamUpdate};
+use crate::registrations::{Registered, Registrations};
+use crate::response::{self, strings, Args, Response, ResponseError};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing_futures::Instrument;
+use xmlrpc::{ServerBuilder, Value};
+
+/// The caller id the master uses when calling nodes
+const CALLER_ID: &str = "/master";
+
+/// An implementation of the ROS Master and Parameter Server APIs. The master serves these APIs
+/// over XMLRPC once it is bound to an address.
+///
+/// For more information read: https://wiki.ros.org/ROS/Master_API and
+/// https://wiki.ros.org/ROS/Parameter%20Server%20API
+#[derive(Default)]
+pub struct Master {
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    uri: Mutex<String>,
+    registrations: Mutex<Registrations>,
+    params: Mutex<ParamServer>,
+    calls: NodeCalls,
+}
+
+impl Master {
+    pub fn new() -> Self {
+        Master::default()
+    }
+
+    /// Starts serving the APIs of the master at the specific `SocketAddr`. Returns a future that
+    /// runs the master until the `shutdown_signal` completes and the address the master is bound
+    /// to.
+    pub fn bind<F>(
+        self,
+        addr: &SocketAddr,
+        shutdown_signal: F,
+    ) -> Result<(impl Future<Output = Result<(), failure::Error>>, SocketAddr), failure::Error>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut server = ServerBuilder::new();
+        let state = self.state;
+
+        register(
+            &mut server,
+            &state,
+            "getUri",
+            "Master URI",
+            |state, mut args| {
+                args.string("caller_id")?;
+                Ok(Value::String(state.uri.lock().unwrap().clone()))
+            },
+        );
+
+        // Master API
+        register(
+            &mut server,
+            &state,
+            "registerService",
+            "Registered service",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let service = resolve(&caller_id, &args.string("service")?);
+                let service_api = args.string("service_api")?;
+                let caller_api = args.string("caller_api")?;
+                let mut registrations = state.registrations.lock().unwrap();
+                register_node(&state.calls, &mut registrations, &caller_id, &caller_api);
+                registrations.register_service(&caller_id, &service, &service_api);
+                Ok(Value::Int(1))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "unregisterService",
+            "Unregistered service",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let service = resolve(&caller_id, &args.string("service")?);
+                let service_api = args.string("service_api")?;
+                let mut registrations = state.registrations.lock().unwrap();
+                let caller_api = registrations.lookup_node(&caller_id).map(str::to_owned);
+                let removed = registrations.unregister_service(&caller_id, &service, &service_api);
+                if let Some(caller_api) = caller_api {
+                    remove_unused_queue(state, &registrations, &caller_api);
+                }
+                Ok(Value::Int(removed as i32))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "registerSubscriber",
+            "Subscribed to topic",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let topic = resolve(&caller_id, &args.string("topic")?);
+                let topic_type = args.string("topic_type")?;
+                let caller_api = args.string("caller_api")?;
+                let mut registrations = state.registrations.lock().unwrap();
+                register_node(&state.calls, &mut registrations, &caller_id, &caller_api);
+                let publishers = registrations.register_subscriber(&caller_id, &topic, &topic_type);
+                Ok(strings(publishers))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "unregisterSubscriber",
+            "Unsubscribed from topic",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let topic = resolve(&caller_id, &args.string("topic")?);
+                let caller_api = args.string("caller_api")?;
+                let mut registrations = state.registrations.lock().unwrap();
+                if registrations.lookup_node(&caller_id) != Some(caller_api.as_str()) {
+                    return Ok(Value::Int(0));
+                }
+                let removed = registrations.unregister_subscriber(&caller_id, &topic);
+                remove_unused_queue(state, &registrations, &caller_api);
+                Ok(Value::Int(removed as i32))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "registerPublisher",
+            "Registered publisher",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let topic = resolve(&caller_id, &args.string("topic")?);
+                let topic_type = args.string("topic_type")?;
+                let caller_api = args.string("caller_api")?;
+                let mut registrations = state.registrations.lock().unwrap();
+                register_node(&state.calls, &mut registrations, &caller_id, &caller_api);
+                let subscribers = registrations.register_publisher(&caller_id, &topic, &topic_type);
+                notify_subscribers(&state.calls, &registrations, &topic);
+                Ok(strings(subscribers))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "unregisterPublisher",
+            "Unregistered publisher",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let topic = resolve(&caller_id, &args.string("topic")?);
+                let caller_api = args.string("caller_api")?;
+                let mut registrations = state.registrations.lock().unwrap();
+                if registrations.lookup_node(&caller_id) != Some(caller_api.as_str()) {
+                    return Ok(Value::Int(0));
+                }
+                let removed = registrations.unregister_publisher(&caller_id, &topic);
+                if removed {
+                    notify_subscribers(&state.calls, &registrations, &topic);
+                }
+                remove_unused_queue(state, &registrations, &caller_api);
+                Ok(Value::Int(removed as i32))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "lookupNode",
+            "Node URI",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let node = resolve(&caller_id, &args.string("node")?);
+                match state.registrations.lock().unwrap().lookup_node(&node) {
+                    Some(api) => Ok(Value::String(api.to_owned())),
+                    None => Err(ResponseError::Client(format!("unknown node [{}]", node))),
+                }
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "getPublishedTopics",
+            "Published topics",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let subgraph = args.string("subgraph")?;
+                let subgraph = if subgraph.is_empty() {
+                    subgraph
+                } else {
+                    resolve(&caller_id, &subgraph)
+                };
+                let topics = state
+                    .registrations
+                    .lock()
+                    .unwrap()
+                    .published_topics(&subgraph);
+                Ok(topic_types(topics))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "getTopicTypes",
+            "Topic types",
+            |state, mut args| {
+                args.string("caller_id")?;
+                let topics = state.registrations.lock().unwrap().topic_types();
+                Ok(topic_types(topics))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "getSystemState",
+            "System state",
+            |state, mut args| {
+                args.string("caller_id")?;
+                let (publishers, subscribers, services) =
+                    state.registrations.lock().unwrap().system_state();
+                Ok(Value::Array(vec![
+                    registered(publishers),
+                    registered(subscribers),
+                    registered(services),
+                ]))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "lookupService",
+            "Service URI",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let service = resolve(&caller_id, &args.string("service")?);
+                match state.registrations.lock().unwrap().lookup_service(&service) {
+                    Some(api) => Ok(Value::String(api.to_owned())),
+                    None => Err(ResponseError::Client(format!(
+                        "no provider for service [{}]",
+                        service
+                    ))),
+                }
+            },
+        );
+
+        // Parameter Server API
+        register(
+            &mut server,
+            &state,
+            "setParam",
+            "Parameter set",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let key = resolve(&caller_id, &args.string("key")?);
+                let value = args.value("value")?;
+                let updates = state.params.lock().unwrap().set(&key, value);
+                notify_param_subscribers(&state.calls, updates);
+                Ok(Value::Int(0))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "getParam",
+            "Parameter value",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let key = resolve(&caller_id, &args.string("key")?);
+                match state.params.lock().unwrap().get(&key) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(ResponseError::Client(format!(
+                        "parameter [{}] is not set",
+                        key
+                    ))),
+                }
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "deleteParam",
+            "Parameter deleted",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let key = resolve(&caller_id, &args.string("key")?);
+                match state.params.lock().unwrap().delete(&key) {
+                    Some(updates) => {
+                        notify_param_subscribers(&state.calls, updates);
+                        Ok(Value::Int(0))
+                    }
+                    None => Err(ResponseError::Client(format!(
+                        "parameter [{}] is not set",
+                        key
+                    ))),
+                }
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "searchParam",
+            "Found parameter",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let key = args.string("key")?;
+                match state.params.lock().unwrap().search(&caller_id, &key) {
+                    Some(found) => Ok(Value::String(found)),
+                    None => Err(ResponseError::Client(format!(
+                        "cannot find parameter [{}] in an upwards search",
+                        key
+                    ))),
+                }
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "subscribeParam",
+            "Subscribed to parameter",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let caller_api = args.string("caller_api")?;
+                let key = resolve(&caller_id, &args.string("key")?);
+                Ok(state
+                    .params
+                    .lock()
+                    .unwrap()
+                    .subscribe(&caller_id, &caller_api, &key))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "unsubscribeParam",
+            "Unsubscribed from parameter",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let caller_api = args.string("caller_api")?;
+                let key = resolve(&caller_id, &args.string("key")?);
+                let removed =
+                    state
+                        .params
+                        .lock()
+                        .unwrap()
+                        .unsubscribe(&caller_id, &caller_api, &key);
+                let registrations = state.registrations.lock().unwrap();
+                remove_unused_queue(state, &registrations, &caller_api);
+                Ok(Value::Int(removed as i32))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "hasParam",
+            "Parameter exists",
+            |state, mut args| {
+                let caller_id = args.string("caller_id")?;
+                let key = resolve(&caller_id, &args.string("key")?);
+                Ok(Value::Bool(state.params.lock().unwrap().has(&key)))
+            },
+        );
+
+        register(
+            &mut server,
+            &state,
+            "getParamNames",
+            "Parameter names",
+            |state, mut args| {
+                args.string("caller_id")?;
+                Ok(strings(state.params.lock().unwrap().names()))
+            },
+        );
+
+        // Start listening for requests
+        let (server, addr) = server.bind(addr, shutdown_signal)?;
+        let host = if addr.ip().is_unspecified() {
+            String::from("localhost")
+        } else {
+            addr.ip().to_string()
+        };
+        *state.uri.lock().unwrap() = format!("http://{}:{}/", host, addr.port());
+        Ok((server, addr))
+    }
+}
+
+/// Registers a handler of the master. Handlers run synchronously, calls to nodes that result from
+/// a call are spawned onto the runtime.
+fn register<H>(
+    server: &mut ServerBuilder,
+    state: &Arc<State>,
+    name: &'static str,
+    message: &'static str,
+    handler: H,
+) where
+    H: Fn(&State, Args) -> Response<Value> + Send + Sync + 'static,
+{
+    let state = state.clone();
+    server.register_value_async(name, move |params| {
+        let span = tracing::trace_span!("handle_call", name = name, params = ?params);
+        let result = span.in_scope(|| handler(&state, Args::new(params)));
+        if let Err(e) = &result {
+            debug!(name = name, "call failed: {}", e);
+        }
+        futures::future::ready(response::encode(result, message))
+    });
+}
+
+/// Registers the api of a node. If a different node with the same name was registered before,
+/// that node is told to shut down and the subscribers of the topics it published are notified.
+fn register_node(
+    calls: &NodeCalls,
+    registrations: &mut Registrations,
+    caller_id: &str,
+    caller_api: &str,
+) {
+    if let Some((previous_api, topics)) = registrations.register_node(caller_id, caller_api) {
+        warn!(
+            caller_id = caller_id,
+            "new node registered with the same name, shutting down {}", previous_api
+        );
+        calls.call(
+            &previous_api,
+            "shutdown",
+            vec![
+                Value::String(CALLER_ID.to_owned()),
+                Value::String("new node registered with same name".to_owned()),
+            ],
+        );
+        calls.remove(&previous_api);
+        for topic in topics {
+            notify_subscribers(calls, registrations, &topic);
+        }
+    }
+}
+
+/// Tells all the subscribers of a topic about the current publishers of the topic
+fn notify_subscribers(calls: &NodeCalls, registrations: &Registrations, topic: &str) {
+    let publishers = strings(registrations.publisher_apis(topic));
+    for api in registrations.subscriber_apis(topic) {
+        calls.call(
+            &api,
+            "publisherUpdate",
+            vec![
+                Value::String(CALLER_ID.to_owned()),
+                Value::String(topic.to_owned()),
+                publishers.clone(),
+            ],
+        );
+    }
+}
+
+/// Tells the subscribers of parameters about the new values of the parameters
+fn notify_param_subscribers(calls: &NodeCalls, updates: Vec<ParamUpdate>) {
+    for (api, key, value) in updates {
+        calls.call(
+            &api,
+            "paramUpdate",
+            vec![
+                Value::String(CALLER_ID.to_owned()),
+                Value::String(key),
+                value,
+            ],
+        );
+    }
+}
+
+/// Removes the queue of calls to the node with the given api once the node has no registrations
+/// and no parameter subscriptions left
+fn remove_unused_queue(state: &State, registrations: &Registrations, caller_api: &str) {
+    if !registrations.has_node_api(caller_api)
+        && !state.params.lock().unwrap().has_subscriber(caller_api)
+    {
+        state.calls.remove(caller_api);
+    }
+}
+
+/// A queued call of a method of the slave API of a node
+type NodeCall = (&'static str, Vec<Value>);
+
+/// Calls methods of the slave API of nodes without waiting for the results. Like rosmaster, every
+/// node has its own queue that is processed by a single task, so a node receives the calls in the
+/// order they were made. Otherwise an outdated `publisherUpdate` could overtake a newer one.
+#[derive(Default)]
+struct NodeCalls {
+    queues: Mutex<HashMap<String, mpsc::UnboundedSender<NodeCall>>>,
+}
+
+impl NodeCalls {
+    /// Queues a call to the node with the given api. The queue is created on first use.
+    fn call(&self, api: &str, method: &'static str, params: Vec<Value>) {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = match queues.get(api) {
+            Some(queue) => queue,
+            None => match spawn_queue(api) {
+                Some(queue) => queues.entry(api.to_owned()).or_insert(queue),
+                None => return,
+            },
+        };
+        let _ = queue.send((method, params));
+    }
+
+    /// Removes the queue of the node with the given api. Calls that were already queued are still
+    /// made.
+    fn remove(&self, api: &str) {
+        self.queues.lock().unwrap().remove(api);
+    }
+}
+
+/// Spawns a task that makes the calls to the node with the given api one by one
+fn spawn_queue(api: &str) -> Option<mpsc::UnboundedSender<NodeCall>> {
+    let uri: xmlrpc::Uri = match api.parse() {
+        Ok(uri) => uri,
+        Err(_) => {
+            error!("invalid node api '{}'", api);
+            return None;
+        }
+    };
+    let (sender, mut receiver) = mpsc::unbounded_channel::<NodeCall>();
+    let api = api.to_owned();
+    tokio::spawn(async move {
+        while let Some((method, params)) = receiver.recv().await {
+            async {
+                match xmlrpc::call_with_params(&uri, method, params).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(fault)) => warn!("node returned a fault: {}", fault.message),
+                    Err(e) => warn!("failed to call node: {}", e),
+                }
+            }
+            .instrument(tracing::trace_span!(
+                "call_node",
+                method = method,
+                api = api.as_str()
+            ))
+            .await
+        }
+    });
+    Some(sender)
+}
+
+/// Resolves a name relative to the namespace of the caller. Private names (starting with `~`) are
+/// resolved relative to the caller itself.
+fn resolve(caller_id: &str, name: &str) -> String {
+    if name.starts_with('/') {
+        name.to_owned()
+    } else if let Some(private) = name.strip_prefix('~') {
+        format!("{}/{}", caller_id, private.trim_start_matches('/'))
+    } else {
+        let namespace = match caller_id.rfind('/') {
+            Some(index) => &caller_id[..index],
+            None => "",
+        };
+        format!("{}/{}", namespace, name)
+    }
+}
+
+fn topic_types(topics: Vec<(String, String)>) -> Value {
+    Value::Array(
+        topics
+            .into_iter()
+            .map(|(topic, topic_type)| {
+                Value::Array(vec![Value::String(topic), Value::String(topic_type)])
+            })
+            .collect(),
+    )
+}
+
+fn registered(registered: Registered) -> Value {
+    Value::Array(
+        registered
+            .into_iter()
+            .map(|(name, nodes)| Value::Array(vec![Value::String(name), strings(nodes)]))
+            .collect(),
+    )
+}
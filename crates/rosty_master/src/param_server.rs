@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashMap};
+use xmlrpc::Value;
+
+/// A notification for a node that subscribed to a parameter: `(caller_api, key, value)`
+pub type ParamUpdate = (String, String, Value);
+
+/// Stores the parameters as a tree of XMLRPC structs and keeps track of the nodes that subscribe
+/// to changes of parameters. All keys are expected to be global names.
+pub struct ParamServer {
+    root: Value,
+
+    /// The apis of the subscribers of every key, by caller id
+    subscribers: BTreeMap<String, HashMap<String, String>>,
+}
+
+impl Default for ParamServer {
+    fn default() -> Self {
+        ParamServer {
+            root: Value::Struct(HashMap::new()),
+            subscribers: BTreeMap::new(),
+        }
+    }
+}
+
+impl ParamServer {
+    /// Returns the value of a parameter
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let mut value = &self.root;
+        for segment in segments(key) {
+            value = match value {
+                Value::Struct(members) => members.get(segment)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Returns true if the parameter exists
+    pub fn has(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets the value of a parameter, creating the namespaces that contain it. Returns the
+    /// notifications for the subscribers of the changed parameters.
+    pub fn set(&mut self, key: &str, value: Value) -> Vec<ParamUpdate> {
+        let mut segments = segments(key).collect::<Vec<_>>();
+        match segments.pop() {
+            None => self.root = as_namespace(value),
+            Some(name) => {
+                let mut parent = &mut self.root;
+                for segment in segments {
+                    let members = namespace(parent);
+                    parent = members
+                        .entry(segment.to_owned())
+                        .or_insert_with(|| Value::Struct(HashMap::new()));
+                }
+                namespace(parent).insert(name.to_owned(), value);
+            }
+        }
+        self.updates(key)
+    }
+
+    /// Removes a parameter. Returns the notifications for the subscribers of the removed
+    /// parameters or `None` if the parameter did not exist.
+    pub fn delete(&mut self, key: &str) -> Option<Vec<ParamUpdate>> {
+        let mut segments = segments(key).collect::<Vec<_>>();
+        match segments.pop() {
+            None => self.root = Value::Struct(HashMap::new()),
+            Some(name) => {
+                let mut parent = &mut self.root;
+                for segment in segments {
+                    parent = match parent {
+                        Value::Struct(members) => members.get_mut(segment)?,
+                        _ => return None,
+                    };
+                }
+                match parent {
+                    Value::Struct(members) => members.remove(name)?,
+                    _ => return None,
+                };
+            }
+        }
+        Some(self.updates(key))
+    }
+
+    /// Searches for a parameter with the given `key`, starting in the namespace `namespace` and
+    /// moving upwards to the root namespace. Returns the full name of the parameter found.
+    pub fn search(&self, namespace: &str, key: &str) -> Option<String> {
+        if key.starts_with('/') {
+            return if self.has(key) {
+                Some(key.to_owned())
+            } else {
+                None
+            };
+        }
+
+        // Only the first segment of the key is searched for, the rest is appended
+        let first = key.split('/').next().unwrap_or_default();
+        let mut namespaces = segments(namespace).collect::<Vec<_>>();
+        loop {
+            let mut candidate = String::new();
+            for segment in namespaces.iter() {
+                candidate.push('/');
+                candidate.push_str(segment);
+            }
+            if self.has(&format!("{}/{}", candidate, first)) {
+                return Some(format!("{}/{}", candidate, key));
+            }
+            namespaces.pop()?;
+        }
+    }
+
+    /// Returns the names of all the parameters that are not namespaces
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        collect_names(&self.root, String::new(), &mut names);
+        names.sort();
+        names
+    }
+
+    /// Subscribes a node to changes of a parameter. Returns the current value of the parameter or
+    /// an empty struct if it does not exist.
+    pub fn subscribe(&mut self, caller_id: &str, caller_api: &str, key: &str) -> Value {
+        self.subscribers
+            .entry(canonical(key))
+            .or_default()
+            .insert(caller_id.to_owned(), caller_api.to_owned());
+        self.get(key)
+            .cloned()
+            .unwrap_or_else(|| Value::Struct(HashMap::new()))
+    }
+
+    /// Unsubscribes a node from changes of a parameter. Returns true if the node was subscribed.
+    pub fn unsubscribe(&mut self, caller_id: &str, caller_api: &str, key: &str) -> bool {
+        let key = canonical(key);
+        let subscribers = match self.subscribers.get_mut(&key) {
+            Some(subscribers) => subscribers,
+            None => return false,
+        };
+        let removed = match subscribers.get(caller_id) {
+            Some(api) if api == caller_api => subscribers.remove(caller_id).is_some(),
+            _ => false,
+        };
+        if subscribers.is_empty() {
+            self.subscribers.remove(&key);
+        }
+        removed
+    }
+
+    /// Returns true if the node with the given api is subscribed to any parameter
+    pub fn has_subscriber(&self, caller_api: &str) -> bool {
+        self.subscribers
+            .values()
+            .any(|subscribers| subscribers.values().any(|api| api == caller_api))
+    }
+
+    /// Returns the notifications for the subscribers of all parameters that are affected by a
+    /// change of the parameter `key`. Subscribers receive the new value of the key they subscribed
+    /// to, an empty struct if it no longer exists.
+    fn updates(&self, key: &str) -> Vec<ParamUpdate> {
+        let key = canonical(key);
+        let mut updates = Vec::new();
+        for (subscribed, subscribers) in self.subscribers.iter() {
+            if !contains(&key, subscribed) && !contains(subscribed, &key) {
+                continue;
+            }
+            let value = self
+                .get(subscribed)
+                .cloned()
+                .unwrap_or_else(|| Value::Struct(HashMap::new()));
+            for api in subscribers.values() {
+                updates.push((api.clone(), subscribed.clone(), value.clone()));
+            }
+        }
+        updates
+    }
+}
+
+/// Returns the segments of a parameter name
+fn segments(key: &str) -> impl Iterator<Item = &str> {
+    key.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Returns a parameter name without trailing or duplicate separators
+fn canonical(key: &str) -> String {
+    let mut result = String::new();
+    for segment in segments(key) {
+        result.push('/');
+        result.push_str(segment);
+    }
+    if result.is_empty() {
+        result.push('/');
+    }
+    result
+}
+
+/// Returns true if the parameter `key` is within the namespace `namespace` or equal to it
+fn contains(namespace: &str, key: &str) -> bool {
+    namespace == "/"
+        || key == namespace
+        || (key.starts_with(namespace) && key[namespace.len()..].starts_with('/'))
+}
+
+/// Returns the members of a namespace, replacing the value with an empty namespace if it is not a
+/// namespace.
+fn namespace(value: &mut Value) -> &mut HashMap<String, Value> {
+    if !matches!(value, Value::Struct(_)) {
+        *value = Value::Struct(HashMap::new());
+    }
+    match value {
+        Value::Struct(members) => members,
+        _ => unreachable!(),
+    }
+}
+
+/// The root of the parameter tree is always a namespace
+fn as_namespace(value: Value) -> Value {
+    match value {
+        Value::Struct(_) => value,
+        _ => Value::Struct(HashMap::new()),
+    }
+}
+
+fn collect_names(value: &Value, prefix: String, names: &mut Vec<String>) {
+    match value {
+        Value::Struct(members) if !members.is_empty() || prefix.is_empty() => {
+            for (name, member) in members {
+                collect_names(member, format!("{}/{}", prefix, name), names);
+            }
+        }
+        _ => names.push(prefix),
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// A list of topics or services together with the nodes that are registered with each of them
+pub type Registered = Vec<(String, Vec<String>)>;
+
+/// Keeps track of the nodes and the topics and services they are registered with
+#[derive(Default)]
+pub struct Registrations {
+    /// The XMLRPC api of every node, by caller id
+    nodes: HashMap<String, String>,
+
+    /// The caller ids of the publishers of every topic
+    publishers: BTreeMap<String, Vec<String>>,
+
+    /// The caller ids of the subscribers of every topic
+    subscribers: BTreeMap<String, Vec<String>>,
+
+    /// The caller id of the provider and the api of every service
+    services: BTreeMap<String, (String, String)>,
+
+    /// The type of every topic that was ever registered
+    topic_types: BTreeMap<String, String>,
+}
+
+impl Registrations {
+    /// Registers the api of a node. If a different node with the same name was registered before,
+    /// all its registrations are removed and its api is returned. The topics it published are
+    /// returned as well.
+    pub fn register_node(
+        &mut self,
+        caller_id: &str,
+        caller_api: &str,
+    ) -> Option<(String, Vec<String>)> {
+        match self
+            .nodes
+            .insert(caller_id.to_owned(), caller_api.to_owned())
+        {
+            Some(previous_api) if previous_api != caller_api => {
+                let topics = remove_all(&mut self.publishers, caller_id);
+                remove_all(&mut self.subscribers, caller_id);
+                self.services
+                    .retain(|_, (provider, _)| provider != caller_id);
+                Some((previous_api, topics))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the api of the node with the given name
+    pub fn lookup_node(&self, caller_id: &str) -> Option<&str> {
+        self.nodes.get(caller_id).map(String::as_str)
+    }
+
+    /// Returns true if a node with the given api is registered
+    pub fn has_node_api(&self, caller_api: &str) -> bool {
+        self.nodes.values().any(|api| api == caller_api)
+    }
+
+    /// Registers a publisher of a topic. Returns the apis of the subscribers of the topic.
+    pub fn register_publisher(
+        &mut self,
+        caller_id: &str,
+        topic: &str,
+        topic_type: &str,
+    ) -> Vec<String> {
+        self.register_topic_type(topic, topic_type);
+        add(&mut self.publishers, topic, caller_id);
+        self.subscriber_apis(topic)
+    }
+
+    /// Removes a publisher of a topic. Returns true if the publisher was registered.
+    pub fn unregister_publisher(&mut self, caller_id: &str, topic: &str) -> bool {
+        let removed = remove(&mut self.publishers, topic, caller_id);
+        self.remove_unused_node(caller_id);
+        removed
+    }
+
+    /// Registers a subscriber of a topic. Returns the apis of the publishers of the topic.
+    pub fn register_subscriber(
+        &mut self,
+        caller_id: &str,
+        topic: &str,
+        topic_type: &str,
+    ) -> Vec<String> {
+        self.register_topic_type(topic, topic_type);
+        add(&mut self.subscribers, topic, caller_id);
+        self.publisher_apis(topic)
+    }
+
+    /// Removes a subscriber of a topic. Returns true if the subscriber was registered.
+    pub fn unregister_subscriber(&mut self, caller_id: &str, topic: &str) -> bool {
+        let removed = remove(&mut self.subscribers, topic, caller_id);
+        self.remove_unused_node(caller_id);
+        removed
+    }
+
+    /// Registers the provider of a service, replacing any previous provider
+    pub fn register_service(&mut self, caller_id: &str, service: &str, service_api: &str) {
+        self.services.insert(
+            service.to_owned(),
+            (caller_id.to_owned(), service_api.to_owned()),
+        );
+    }
+
+    /// Removes the provider of a service if it is registered with the given api. Returns true if
+    /// the service was removed.
+    pub fn unregister_service(
+        &mut self,
+        caller_id: &str,
+        service: &str,
+        service_api: &str,
+    ) -> bool {
+        let removed = match self.services.get(service) {
+            Some((_, api)) if api == service_api => {
+                self.services.remove(service);
+                true
+            }
+            _ => false,
+        };
+        self.remove_unused_node(caller_id);
+        removed
+    }
+
+    /// Returns the api of the provider of a service
+    pub fn lookup_service(&self, service: &str) -> Option<&str> {
+        self.services.get(service).map(|(_, api)| api.as_str())
+    }
+
+    /// Returns the apis of all the publishers of a topic
+    pub fn publisher_apis(&self, topic: &str) -> Vec<String> {
+        self.apis(&self.publishers, topic)
+    }
+
+    /// Returns the apis of all the subscribers of a topic
+    pub fn subscriber_apis(&self, topic: &str) -> Vec<String> {
+        self.apis(&self.subscribers, topic)
+    }
+
+    /// Returns the topics and their types for which a publisher is registered. Only topics within
+    /// the `subgraph` namespace are returned, an empty `subgraph` returns all topics.
+    pub fn published_topics(&self, subgraph: &str) -> Vec<(String, String)> {
+        let prefix = subgraph.trim_end_matches('/');
+        self.publishers
+            .keys()
+            .filter(|topic| {
+                prefix.is_empty()
+                    || topic.as_str() == prefix
+                    || topic.starts_with(&format!("{}/", prefix))
+            })
+            .filter_map(|topic| {
+                self.topic_types
+                    .get(topic)
+                    .map(|topic_type| (topic.clone(), topic_type.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns all the known topics and their types
+    pub fn topic_types(&self) -> Vec<(String, String)> {
+        self.topic_types
+            .iter()
+            .map(|(topic, topic_type)| (topic.clone(), topic_type.clone()))
+            .collect()
+    }
+
+    /// Returns the publishers, subscribers and services that are registered
+    pub fn system_state(&self) -> (Registered, Registered, Registered) {
+        let registered = |map: &BTreeMap<String, Vec<String>>| {
+            map.iter()
+                .map(|(name, nodes)| (name.clone(), nodes.clone()))
+                .collect()
+        };
+        let services = self
+            .services
+            .iter()
+            .map(|(service, (provider, _))| (service.clone(), vec![provider.clone()]))
+            .collect();
+        (
+            registered(&self.publishers),
+            registered(&self.subscribers),
+            services,
+        )
+    }
+
+    fn register_topic_type(&mut self, topic: &str, topic_type: &str) {
+        // Subscribers that accept any type do not determine the type of the topic
+        if topic_type != "*" || !self.topic_types.contains_key(topic) {
+            self.topic_types
+                .insert(topic.to_owned(), topic_type.to_owned());
+        }
+    }
+
+    fn apis(&self, map: &BTreeMap<String, Vec<String>>, topic: &str) -> Vec<String> {
+        map.get(topic)
+            .into_iter()
+            .flatten()
+            .filter_map(|caller_id| self.nodes.get(caller_id).cloned())
+            .collect()
+    }
+
+    /// Forgets about a node once it no longer has any registrations
+    fn remove_unused_node(&mut self, caller_id: &str) {
+        let registered = |map: &BTreeMap<String, Vec<String>>| {
+            map.values()
+                .any(|nodes| nodes.iter().any(|node| node == caller_id))
+        };
+        if !registered(&self.publishers)
+            && !registered(&self.subscribers)
+            && !self
+                .services
+                .values()
+                .any(|(provider, _)| provider == caller_id)
+        {
+            self.nodes.remove(caller_id);
+        }
+    }
+}
+
+fn add(map: &mut BTreeMap<String, Vec<String>>, name: &str, caller_id: &str) {
+    let nodes = map.entry(name.to_owned()).or_default();
+    if !nodes.iter().any(|node| node == caller_id) {
+        nodes.push(caller_id.to_owned());
+    }
+}
+
+fn remove(map: &mut BTreeMap<String, Vec<String>>, name: &str, caller_id: &str) -> bool {
+    let nodes = match map.get_mut(name) {
+        Some(nodes) => nodes,
+        None => return false,
+    };
+    let count = nodes.len();
+    nodes.retain(|node| node != caller_id);
+    let removed = nodes.len() != count;
+    if nodes.is_empty() {
+        map.remove(name);
+    }
+    removed
+}
+
+/// Removes the node from all the entries of the map and returns the names of the entries it was
+/// removed from.
+fn remove_all(map: &mut BTreeMap<String, Vec<String>>, caller_id: &str) -> Vec<String> {
+    let names: Vec<String> = map
+        .iter()
+        .filter(|(_, nodes)| nodes.iter().any(|node| node == caller_id))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in names.iter() {
+        remove(map, name, caller_id);
+    }
+    names
+}
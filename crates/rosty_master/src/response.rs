@@ -0,0 +1,67 @@
+//! ROS uses a protocol on top of XMLRPC to encode return values. XMLRPC calls always return a tuple
+//! in the form of: `(code, statusMessage, value)`. This module converts the results of the handlers
+//! of the master to this form and helps with reading the arguments of calls.
+
+use xmlrpc::{Params, Value};
+
+pub type Response<T> = Result<T, ResponseError>;
+
+/// A `ResponseError` is an error caused by the client, for instance by passing invalid arguments.
+#[derive(Debug, Clone, Eq, PartialEq, Fail)]
+pub enum ResponseError {
+    #[fail(display = "client error: {}", 0)]
+    Client(String),
+}
+
+const ERROR_CODE: i32 = -1;
+const SUCCESS_CODE: i32 = 1;
+
+/// Encodes the result of a call as a `(code, statusMessage, value)` tuple
+pub fn encode(response: Response<Value>, message: &str) -> xmlrpc::Response {
+    let (code, message, data) = match response {
+        Ok(data) => (SUCCESS_CODE, message.to_owned(), data),
+        Err(ResponseError::Client(message)) => (ERROR_CODE, message, Value::Int(0)),
+    };
+    Ok(vec![Value::Array(vec![
+        Value::Int(code),
+        Value::String(message),
+        data,
+    ])])
+}
+
+/// Reads the arguments of a call in order
+pub struct Args(std::vec::IntoIter<Value>);
+
+impl Args {
+    pub fn new(params: Params) -> Self {
+        // Depending on the client the arguments may be wrapped in an array
+        let params = match params.as_slice() {
+            [Value::Array(items)] => items.clone(),
+            _ => params,
+        };
+        Args(params.into_iter())
+    }
+
+    /// Returns the next argument
+    pub fn value(&mut self, name: &str) -> Response<Value> {
+        self.0
+            .next()
+            .ok_or_else(|| ResponseError::Client(format!("missing argument '{}'", name)))
+    }
+
+    /// Returns the next argument, which must be a string
+    pub fn string(&mut self, name: &str) -> Response<String> {
+        match self.value(name)? {
+            Value::String(value) => Ok(value),
+            _ => Err(ResponseError::Client(format!(
+                "argument '{}' must be a string",
+                name
+            ))),
+        }
+    }
+}
+
+/// Converts a list of strings to an XMLRPC array
+pub fn strings<I: IntoIterator<Item = String>>(items: I) -> Value {
+    Value::Array(items.into_iter().map(Value::String).collect())
+}
@@ -0,0 +1,261 @@
+use rosty_master::Master;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use xmlrpc::{ServerBuilder, Uri, Value};
+
+/// Calls a method of the master and returns the value of the `(code, statusMessage, value)` tuple
+/// or the status message if the call failed.
+async fn call(uri: &Uri, method: &str, params: Vec<Value>) -> Result<Value, String> {
+    let response = xmlrpc::call_with_params(uri, method, params)
+        .await
+        .unwrap()
+        .unwrap();
+    match response.as_slice() {
+        [Value::Array(items)] => match items.as_slice() {
+            [Value::Int(1), _, value] => Ok(value.clone()),
+            [Value::Int(_), Value::String(message), _] => Err(message.clone()),
+            _ => panic!("invalid response: {:?}", items),
+        },
+        _ => panic!("invalid response: {:?}", response),
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<Value> {
+    values
+        .iter()
+        .map(|v| Value::String(v.to_string()))
+        .collect()
+}
+
+/// Starts a fake node that forwards the calls it receives on the returned channel
+fn run_node() -> (String, mpsc::UnboundedReceiver<(String, Vec<Value>)>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut server = ServerBuilder::new();
+    server.set_on_missing(move |name, params| {
+        let _ = tx.send((name, params));
+        async {
+            Ok(vec![Value::Array(vec![
+                Value::Int(1),
+                Value::String(String::new()),
+                Value::Int(0),
+            ])])
+        }
+    });
+    let (server, addr) = server
+        .bind(
+            &SocketAddr::from(([127, 0, 0, 1], 0)),
+            futures::future::pending(),
+        )
+        .unwrap();
+    tokio::spawn(server);
+    (format!("http://127.0.0.1:{}/", addr.port()), rx)
+}
+
+async fn next_call(rx: &mut mpsc::UnboundedReceiver<(String, Vec<Value>)>) -> (String, Vec<Value>) {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no call was received")
+        .unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn master() {
+    let (server, addr) = Master::new()
+        .bind(
+            &SocketAddr::from(([127, 0, 0, 1], 0)),
+            futures::future::pending(),
+        )
+        .unwrap();
+    tokio::spawn(server);
+    let uri: Uri = format!("http://127.0.0.1:{}/", addr.port())
+        .parse()
+        .unwrap();
+
+    let uri_value = call(&uri, "getUri", strings(&["/test"])).await.unwrap();
+    assert_eq!(uri_value, Value::String(uri.to_string()));
+
+    // Subscribe before anybody publishes
+    let (subscriber_api, mut subscriber_calls) = run_node();
+    let publishers = call(
+        &uri,
+        "registerSubscriber",
+        strings(&["/sub", "/foo", "std_msgs/String", &subscriber_api]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(publishers, Value::Array(vec![]));
+
+    // Registering a publisher notifies the subscriber
+    let (publisher_api, _publisher_calls) = run_node();
+    let subscribers = call(
+        &uri,
+        "registerPublisher",
+        strings(&["/pub", "foo", "std_msgs/String", &publisher_api]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(subscribers, Value::Array(strings(&[&subscriber_api])));
+    let (method, params) = next_call(&mut subscriber_calls).await;
+    assert_eq!(method, "publisherUpdate");
+    assert_eq!(
+        params,
+        vec![
+            Value::String("/master".into()),
+            Value::String("/foo".into()),
+            Value::Array(strings(&[&publisher_api]))
+        ]
+    );
+
+    assert_eq!(
+        call(&uri, "lookupNode", strings(&["/test", "/pub"])).await,
+        Ok(Value::String(publisher_api.clone()))
+    );
+    assert_eq!(
+        call(&uri, "getTopicTypes", strings(&["/test"])).await,
+        Ok(Value::Array(vec![Value::Array(strings(&[
+            "/foo",
+            "std_msgs/String"
+        ]))]))
+    );
+
+    // Services
+    call(
+        &uri,
+        "registerService",
+        strings(&["/pub", "/add", "rosrpc://localhost:1234", &publisher_api]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        call(&uri, "lookupService", strings(&["/test", "/add"])).await,
+        Ok(Value::String("rosrpc://localhost:1234".into()))
+    );
+    assert_eq!(
+        call(&uri, "getSystemState", strings(&["/test"])).await,
+        Ok(Value::Array(vec![
+            Value::Array(vec![Value::Array(vec![
+                Value::String("/foo".into()),
+                Value::Array(strings(&["/pub"]))
+            ])]),
+            Value::Array(vec![Value::Array(vec![
+                Value::String("/foo".into()),
+                Value::Array(strings(&["/sub"]))
+            ])]),
+            Value::Array(vec![Value::Array(vec![
+                Value::String("/add".into()),
+                Value::Array(strings(&["/pub"]))
+            ])]),
+        ]))
+    );
+    call(
+        &uri,
+        "unregisterService",
+        strings(&["/pub", "/add", "rosrpc://localhost:1234"]),
+    )
+    .await
+    .unwrap();
+    assert!(call(&uri, "lookupService", strings(&["/test", "/add"]))
+        .await
+        .is_err());
+
+    // Unregistering the publisher notifies the subscriber
+    assert_eq!(
+        call(
+            &uri,
+            "unregisterPublisher",
+            strings(&["/pub", "/foo", &publisher_api])
+        )
+        .await,
+        Ok(Value::Int(1))
+    );
+    let (method, params) = next_call(&mut subscriber_calls).await;
+    assert_eq!(method, "publisherUpdate");
+    assert_eq!(params[2], Value::Array(vec![]));
+    assert!(call(&uri, "lookupNode", strings(&["/test", "/pub"]))
+        .await
+        .is_err());
+
+    // Parameters are resolved relative to the namespace of the caller
+    call(
+        &uri,
+        "subscribeParam",
+        strings(&["/sub", &subscriber_api, "/ns/value"]),
+    )
+    .await
+    .unwrap();
+    call(
+        &uri,
+        "setParam",
+        vec![
+            Value::String("/ns/node".into()),
+            Value::String("value".into()),
+            Value::Int(42),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        call(&uri, "getParam", strings(&["/test", "/ns/value"])).await,
+        Ok(Value::Int(42))
+    );
+    assert_eq!(
+        call(&uri, "hasParam", strings(&["/test", "/ns/other"])).await,
+        Ok(Value::Bool(false))
+    );
+    assert_eq!(
+        call(&uri, "searchParam", strings(&["/ns/deep/node", "value"])).await,
+        Ok(Value::String("/ns/value".into()))
+    );
+    assert_eq!(
+        call(&uri, "getParamNames", strings(&["/test"])).await,
+        Ok(Value::Array(strings(&["/ns/value"])))
+    );
+    let (method, params) = next_call(&mut subscriber_calls).await;
+    assert_eq!(method, "paramUpdate");
+    assert_eq!(
+        params[1..],
+        [Value::String("/ns/value".into()), Value::Int(42)]
+    );
+
+    // Updates are delivered to a node in the order they were made, so the last update holds the
+    // value that was set last
+    let uri_ref = &uri;
+    futures::future::join_all((0..50).map(|i| async move {
+        call(
+            uri_ref,
+            "setParam",
+            vec![
+                Value::String("/test".into()),
+                Value::String("/ns/value".into()),
+                Value::Int(i),
+            ],
+        )
+        .await
+        .unwrap()
+    }))
+    .await;
+    let mut last_update = None;
+    for _ in 0..50 {
+        let (method, params) = next_call(&mut subscriber_calls).await;
+        assert_eq!(method, "paramUpdate");
+        last_update = Some(params[2].clone());
+    }
+    assert_eq!(
+        last_update,
+        call(&uri, "getParam", strings(&["/test", "/ns/value"]))
+            .await
+            .ok()
+    );
+
+    // Deleting a namespace notifies the subscribers of the parameters within
+    call(&uri, "deleteParam", strings(&["/test", "/ns"]))
+        .await
+        .unwrap();
+    assert!(call(&uri, "getParam", strings(&["/test", "/ns/value"]))
+        .await
+        .is_err());
+    let (method, params) = next_call(&mut subscriber_calls).await;
+    assert_eq!(method, "paramUpdate");
+    assert_eq!(params[2], Value::Struct(Default::default()));
+}
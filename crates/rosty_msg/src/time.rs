@@ -82,7 +82,7 @@ impl Duration {
     }
 
     #[inline]
-    fn nanos(self) -> i64 {
+    pub fn nanos(self) -> i64 {
         i64::from(self.sec) * BILLION + i64::from(self.nsec)
     }
 
@@ -0,0 +1,485 @@
+//! Runtime representation of messages whose type is only known from their `message_definition`.
+
+use crate::error::Result;
+use crate::helpers::{calculate_md5, MessageMap};
+use crate::message_path::MessagePath;
+use crate::msg::{DataType, FieldCase, FieldInfo, Msg};
+use error_chain::bail;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{self, Read};
+
+/// A message value that is decoded at runtime
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    String(String),
+    Time {
+        sec: u32,
+        nsec: u32,
+    },
+    Duration {
+        sec: i32,
+        nsec: i32,
+    },
+    Array(Vec<Value>),
+    /// The fields of a message in the order of its definition
+    Message(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Returns the value of the field with the given `name` if this is a message
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Message(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+/// The schema of a message type together with all the message types it depends on. Built from
+/// the full `message_definition` that is exchanged in connection headers.
+#[derive(Clone, Debug)]
+pub struct DynamicMsg {
+    path: MessagePath,
+    messages: HashMap<MessagePath, Msg>,
+}
+
+impl DynamicMsg {
+    /// Parses the `definition` of the message type `msg_type`. The definitions of dependencies are
+    /// expected to follow the definition of the message itself in blocks that start with a
+    /// `MSG: <type>` line and are separated by lines of `=`, as produced by
+    /// `generate_message_definition`.
+    pub fn new(msg_type: &str, definition: &str) -> Result<DynamicMsg> {
+        let path = MessagePath::try_from(msg_type)?;
+
+        let mut blocks = vec![(Some(path.clone()), String::new())];
+        for line in definition.lines() {
+            if line.starts_with("==") && line.chars().all(|c| c == '=') {
+                blocks.push((None, String::new()));
+                continue;
+            }
+            let (block_path, source) = blocks.last_mut().unwrap();
+            if block_path.is_none() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                match line.strip_prefix("MSG:") {
+                    Some(name) => *block_path = Some(MessagePath::try_from(name.trim())?),
+                    None => bail!("Message definition block does not start with 'MSG:'"),
+                }
+                continue;
+            }
+            source.push_str(line);
+            source.push('\n');
+        }
+
+        let mut messages = HashMap::new();
+        for (block_path, source) in blocks {
+            let block_path = match block_path {
+                Some(block_path) => block_path,
+                None => bail!("Message definition contains an empty block"),
+            };
+            messages.insert(block_path.clone(), Msg::new(block_path, &source)?);
+        }
+
+        let result = DynamicMsg { path, messages };
+        result.validate(&result.path, &mut Vec::new(), &mut HashSet::new())?;
+        Ok(result)
+    }
+
+    /// Ensures that the definitions of all dependencies of the message at `path` are available and
+    /// that no message contains itself, which would make decoding recurse endlessly. `stack` holds
+    /// the messages that contain the message at `path`.
+    fn validate(
+        &self,
+        path: &MessagePath,
+        stack: &mut Vec<MessagePath>,
+        visited: &mut HashSet<MessagePath>,
+    ) -> Result<()> {
+        if stack.contains(path) {
+            bail!("Message definition of '{}' contains itself", path);
+        }
+        if !visited.insert(path.clone()) {
+            return Ok(());
+        }
+        let msg = match self.messages.get(path) {
+            Some(msg) => msg,
+            None => bail!("Message definition does not contain '{}'", path),
+        };
+        stack.push(path.clone());
+        for dependency in msg.dependencies() {
+            self.validate(&dependency, stack, visited)?;
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    /// Returns the type of the message
+    pub fn msg_type(&self) -> String {
+        self.path.to_string()
+    }
+
+    /// Returns the parsed message at the root of the schema
+    pub fn msg(&self) -> &Msg {
+        &self.messages[&self.path]
+    }
+
+    /// Computes the md5sum of the message from its definition
+    pub fn md5sum(&self) -> Result<String> {
+        let message_map = MessageMap {
+            messages: self.messages.clone(),
+            services: HashSet::new(),
+        };
+        Ok(calculate_md5(&message_map)?.remove(&self.path).unwrap())
+    }
+
+    /// Decodes a serialized message, without its length prefix
+    pub fn decode<R: io::Read>(&self, mut r: R) -> io::Result<Value> {
+        self.decode_message(&self.path, &mut r)
+    }
+
+    /// Serializes a message, without its length prefix
+    pub fn encode<W: io::Write>(&self, value: &Value, mut w: W) -> io::Result<()> {
+        self.encode_message(&self.path, value, &mut w)
+    }
+
+    /// Returns the message a struct `datatype` used within `package` refers to
+    fn resolve(&self, package: &str, datatype: &DataType) -> MessagePath {
+        match datatype {
+            DataType::LocalStruct(name) => MessagePath::new(package, name),
+            DataType::RemoteStruct(path) => path.clone(),
+            _ => unreachable!("only structs refer to other messages"),
+        }
+    }
+
+    fn decode_message<R: io::Read>(&self, path: &MessagePath, r: &mut R) -> io::Result<Value> {
+        let msg = &self.messages[path];
+        let mut fields = Vec::with_capacity(msg.fields.len());
+        for field in msg.fields.iter() {
+            let value = match field.case {
+                FieldCase::Const(_) => continue,
+                FieldCase::Unit => self.decode_value(&path.package, &field.datatype, r)?,
+                FieldCase::Vector => {
+                    let len = read_u32(r)? as usize;
+                    self.decode_array(&path.package, &field.datatype, len, r)?
+                }
+                FieldCase::Array(len) => {
+                    self.decode_array(&path.package, &field.datatype, len, r)?
+                }
+            };
+            fields.push((field.name.clone(), value));
+        }
+        Ok(Value::Message(fields))
+    }
+
+    fn decode_array<R: io::Read>(
+        &self,
+        package: &str,
+        datatype: &DataType,
+        len: usize,
+        r: &mut R,
+    ) -> io::Result<Value> {
+        // The length is read from the message, so do not trust it for sizing the allocation
+        let mut items = Vec::with_capacity(len.min(MAX_PREALLOCATED_ITEMS));
+        for _ in 0..len {
+            items.push(self.decode_value(package, datatype, r)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn decode_value<R: io::Read>(
+        &self,
+        package: &str,
+        datatype: &DataType,
+        r: &mut R,
+    ) -> io::Result<Value> {
+        Ok(match datatype {
+            DataType::Bool => Value::Bool(read_bytes::<_, 1>(r)?[0] != 0),
+            DataType::I8(_) => Value::I8(i8::from_le_bytes(read_bytes(r)?)),
+            DataType::I16 => Value::I16(i16::from_le_bytes(read_bytes(r)?)),
+            DataType::I32 => Value::I32(i32::from_le_bytes(read_bytes(r)?)),
+            DataType::I64 => Value::I64(i64::from_le_bytes(read_bytes(r)?)),
+            DataType::U8(_) => Value::U8(u8::from_le_bytes(read_bytes(r)?)),
+            DataType::U16 => Value::U16(u16::from_le_bytes(read_bytes(r)?)),
+            DataType::U32 => Value::U32(read_u32(r)?),
+            DataType::U64 => Value::U64(u64::from_le_bytes(read_bytes(r)?)),
+            DataType::F32 => Value::F32(f32::from_le_bytes(read_bytes(r)?)),
+            DataType::F64 => Value::F64(f64::from_le_bytes(read_bytes(r)?)),
+            DataType::String => {
+                let len = read_u32(r)? as usize;
+                let mut data = Vec::with_capacity(len.min(MAX_PREALLOCATED_ITEMS));
+                if r.by_ref().take(len as u64).read_to_end(&mut data)? < len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Value::String(
+                    String::from_utf8(data)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                )
+            }
+            DataType::Time => Value::Time {
+                sec: read_u32(r)?,
+                nsec: read_u32(r)?,
+            },
+            DataType::Duration => Value::Duration {
+                sec: i32::from_le_bytes(read_bytes(r)?),
+                nsec: i32::from_le_bytes(read_bytes(r)?),
+            },
+            DataType::LocalStruct(_) | DataType::RemoteStruct(_) => {
+                self.decode_message(&self.resolve(package, datatype), r)?
+            }
+        })
+    }
+
+    fn encode_message<W: io::Write>(
+        &self,
+        path: &MessagePath,
+        value: &Value,
+        w: &mut W,
+    ) -> io::Result<()> {
+        let msg = &self.messages[path];
+        for field in msg.fields.iter() {
+            if let FieldCase::Const(_) = field.case {
+                continue;
+            }
+            let field_value = value.field(&field.name).ok_or_else(|| {
+                invalid_input(format!("missing field '{}' of '{}'", field.name, path))
+            })?;
+            self.encode_field(&path.package, field, field_value, w)?;
+        }
+        Ok(())
+    }
+
+    fn encode_field<W: io::Write>(
+        &self,
+        package: &str,
+        field: &FieldInfo,
+        value: &Value,
+        w: &mut W,
+    ) -> io::Result<()> {
+        let items = match (&field.case, value) {
+            (FieldCase::Unit, value) => {
+                return self.encode_value(package, &field.datatype, value, w)
+            }
+            (FieldCase::Vector, Value::Array(items)) => {
+                w.write_all(&(items.len() as u32).to_le_bytes())?;
+                items
+            }
+            (FieldCase::Array(len), Value::Array(items)) if items.len() == *len => items,
+            _ => {
+                return Err(invalid_input(format!(
+                    "invalid value for field '{}'",
+                    field.name
+                )))
+            }
+        };
+        for item in items {
+            self.encode_value(package, &field.datatype, item, w)?;
+        }
+        Ok(())
+    }
+
+    fn encode_value<W: io::Write>(
+        &self,
+        package: &str,
+        datatype: &DataType,
+        value: &Value,
+        w: &mut W,
+    ) -> io::Result<()> {
+        match (datatype, value) {
+            (DataType::Bool, Value::Bool(v)) => w.write_all(&[*v as u8]),
+            (DataType::I8(_), Value::I8(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::I16, Value::I16(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::I32, Value::I32(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::I64, Value::I64(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::U8(_), Value::U8(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::U16, Value::U16(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::U32, Value::U32(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::U64, Value::U64(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::F32, Value::F32(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::F64, Value::F64(v)) => w.write_all(&v.to_le_bytes()),
+            (DataType::String, Value::String(v)) => {
+                w.write_all(&(v.len() as u32).to_le_bytes())?;
+                w.write_all(v.as_bytes())
+            }
+            (DataType::Time, Value::Time { sec, nsec }) => {
+                w.write_all(&sec.to_le_bytes())?;
+                w.write_all(&nsec.to_le_bytes())
+            }
+            (DataType::Duration, Value::Duration { sec, nsec }) => {
+                w.write_all(&sec.to_le_bytes())?;
+                w.write_all(&nsec.to_le_bytes())
+            }
+            (DataType::LocalStruct(_), Value::Message(_))
+            | (DataType::RemoteStruct(_), Value::Message(_)) => {
+                self.encode_message(&self.resolve(package, datatype), value, w)
+            }
+            _ => Err(invalid_input(format!(
+                "value {:?} does not match type {:?}",
+                value, datatype
+            ))),
+        }
+    }
+}
+
+/// The maximum number of items that is allocated up front for a length read from a message.
+/// Larger arrays and strings grow while they are decoded, so a corrupt length fails on the missing
+/// data instead of allocating gigabytes of memory.
+const MAX_PREALLOCATED_ITEMS: usize = 4096;
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn read_bytes<R: io::Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+    let mut data = [0u8; N];
+    r.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn read_u32<R: io::Read>(r: &mut R) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(r)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = "Header header\nuint8 MODE=1\nstring[] names\nfloat64[2] \
+                              position\nPoint point\n\n\
+                              ================================================================================\n\
+                              MSG: std_msgs/Header\nuint32 seq\ntime stamp\nstring frame_id\n\n\
+                              ================================================================================\n\
+                              MSG: test_msgs/Point\nint8 x\n";
+
+    fn value() -> Value {
+        Value::Message(vec![
+            (
+                "header".into(),
+                Value::Message(vec![
+                    ("seq".into(), Value::U32(3)),
+                    ("stamp".into(), Value::Time { sec: 1, nsec: 2 }),
+                    ("frame_id".into(), Value::String("map".into())),
+                ]),
+            ),
+            (
+                "names".into(),
+                Value::Array(vec![Value::String("a".into()), Value::String("bc".into())]),
+            ),
+            (
+                "position".into(),
+                Value::Array(vec![Value::F64(1.0), Value::F64(-2.5)]),
+            ),
+            (
+                "point".into(),
+                Value::Message(vec![("x".into(), Value::I8(-1))]),
+            ),
+        ])
+    }
+
+    fn bytes() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(b"map");
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(b"a");
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(b"bc");
+        data.extend_from_slice(&1f64.to_le_bytes());
+        data.extend_from_slice(&(-2.5f64).to_le_bytes());
+        data.push(0xff);
+        data
+    }
+
+    #[test]
+    fn parses_definition() {
+        let msg = DynamicMsg::new("test_msgs/Sample", DEFINITION).unwrap();
+        assert_eq!(msg.msg_type(), "test_msgs/Sample");
+        assert_eq!(msg.msg().fields.len(), 5);
+        assert!(DynamicMsg::new("test_msgs/Sample", "Header header\n").is_err());
+    }
+
+    #[test]
+    fn rejects_recursive_definitions() {
+        let definition = "Node next\n\
+                          ================================================================================\n\
+                          MSG: test_msgs/Node\nNode next\n";
+        assert!(DynamicMsg::new("test_msgs/List", definition).is_err());
+
+        let definition = "B b\n\
+                          ================================================================================\n\
+                          MSG: test_msgs/B\nA a\n";
+        assert!(DynamicMsg::new("test_msgs/A", definition).is_err());
+
+        // A message may be used several times as long as it does not contain itself
+        let definition = "Point first\nPoint second\n\
+                          ================================================================================\n\
+                          MSG: test_msgs/Point\nint8 x\n";
+        assert!(DynamicMsg::new("test_msgs/Line", definition).is_ok());
+    }
+
+    #[test]
+    fn decodes_messages() {
+        let msg = DynamicMsg::new("test_msgs/Sample", DEFINITION).unwrap();
+        let value = msg.decode(io::Cursor::new(bytes())).unwrap();
+        assert_eq!(value, self::value());
+        assert_eq!(
+            value.field("header").unwrap().field("frame_id"),
+            Some(&Value::String("map".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_lengths() {
+        let msg = DynamicMsg::new("test_msgs/Text", "string text\nuint8[] data\n").unwrap();
+        let mut data = u32::MAX.to_le_bytes().to_vec();
+        data.extend_from_slice(b"abc");
+        let err = msg.decode(io::Cursor::new(data)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&[1, 2, 3]);
+        let err = msg.decode(io::Cursor::new(data)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn encodes_messages() {
+        let msg = DynamicMsg::new("test_msgs/Sample", DEFINITION).unwrap();
+        let mut data = Vec::new();
+        msg.encode(&value(), &mut data).unwrap();
+        assert_eq!(data, bytes());
+        assert!(msg
+            .encode(&Value::Message(vec![]), &mut Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn computes_md5sum() {
+        let msg = DynamicMsg::new(
+            "std_msgs/Header",
+            "uint32 seq\ntime stamp\nstring frame_id\n",
+        )
+        .unwrap();
+        assert_eq!(msg.md5sum().unwrap(), "2176decaecbce78abc3b96ef049fabed");
+    }
+}
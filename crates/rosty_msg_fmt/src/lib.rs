@@ -1,3 +1,4 @@
+pub mod dynamic;
 pub mod error;
 pub mod helpers;
 pub mod message_path;